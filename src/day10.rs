@@ -1,84 +1,914 @@
 //! Day 10 - Cathode-ray tube
 
-use std::collections::HashMap;
+use crate::line_error::LineError;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::str::FromStr;
 
-#[derive(Debug)]
+/// The CPU's registers. The puzzle only ever uses X
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    X,
+    Y,
+    Z,
+}
+
+impl Register {
+    pub const ALL: [Register; 3] = [Self::X, Self::Y, Self::Z];
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::X => write!(f, "x"),
+            Self::Y => write!(f, "y"),
+            Self::Z => write!(f, "z"),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = AsmErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x" => Ok(Self::X),
+            "y" => Ok(Self::Y),
+            "z" => Ok(Self::Z),
+            other => Err(AsmErrorKind::BadRegister(other.into())),
+        }
+    }
+}
+
+/// The kinds of operand an instruction can take
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register,
+
+    /// A signed number
+    Value,
+
+    /// Where to jump to, either a label or an instruction's address
+    Target,
+}
+
+/// A single operand, once it's been parsed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Argument {
+    Register(Register),
+    Value(i32),
+    Target(usize),
+}
+
+impl Argument {
+    pub fn kind(&self) -> Operand {
+        match self {
+            Self::Register(_) => Operand::Register,
+            Self::Value(_) => Operand::Value,
+            Self::Target(_) => Operand::Target,
+        }
+    }
+
+    /// Any old argument of the given kind
+    fn placeholder(kind: Operand) -> Self {
+        match kind {
+            Operand::Register => Self::Register(Register::X),
+            Operand::Value => Self::Value(0),
+            Operand::Target => Self::Target(0),
+        }
+    }
+}
+
+/// Hands out an instruction's arguments in order, see [InstructionInfo::build]
+struct Arguments<'a>(std::slice::Iter<'a, Argument>);
+
+impl Arguments<'_> {
+    fn register(&mut self) -> Register {
+        match self.0.next() {
+            Some(&Argument::Register(register)) => register,
+            other => panic!("expected a register, got {other:?}"),
+        }
+    }
+
+    fn value(&mut self) -> i32 {
+        match self.0.next() {
+            Some(&Argument::Value(value)) => value,
+            other => panic!("expected a value, got {other:?}"),
+        }
+    }
+
+    fn target(&mut self) -> usize {
+        match self.0.next() {
+            Some(&Argument::Target(target)) => target,
+            other => panic!("expected a target, got {other:?}"),
+        }
+    }
+}
+
+/// Everything the assembler and CPU need to know about an instruction
+#[derive(Copy, Clone, Debug)]
+pub struct InstructionInfo {
+    pub mnemonic: &'static str,
+
+    /// How many cycles it takes before the instruction takes effect
+    pub cycles: usize,
+    pub operands: &'static [Operand],
+
+    /// Puts the instruction together from arguments matching `operands`
+    constructor: fn(&mut Arguments) -> Opcode,
+}
+
+impl InstructionInfo {
+    /// Put the instruction together from its arguments, if they're the
+    /// right kinds for it
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day10::*;
+    ///
+    /// let add = INSTRUCTION_SET.iter().find(|info| info.mnemonic == "add").unwrap();
+    /// assert_eq!(add.build(&[Argument::Register(Register::Z), Argument::Value(3)]), Some(Opcode::Add(Register::Z, 3)));
+    /// assert_eq!(add.build(&[Argument::Value(3)]), None);
+    /// ```
+    pub fn build(&self, args: &[Argument]) -> Option<Opcode> {
+        let matches = args.len() == self.operands.len()
+            && args.iter().zip(self.operands).all(|(arg, &kind)| arg.kind() == kind);
+
+        matches.then(|| (self.constructor)(&mut Arguments(args.iter())))
+    }
+
+    /// The instruction with placeholder arguments, just to see which
+    /// opcode it makes
+    fn example(&self) -> Opcode {
+        let args = self.operands
+            .iter()
+            .map(|&kind| Argument::placeholder(kind))
+            .collect::<Vec<_>>();
+
+        (self.constructor)(&mut Arguments(args.iter()))
+    }
+}
+
+/// Every instruction the CPU understands. Jumps go to an absolute address,
+/// and the conditional ones only jump if the register is (or isn't) zero
+pub const INSTRUCTION_SET: &[InstructionInfo] = &[
+    InstructionInfo {
+        mnemonic: "noop",
+        cycles: 1,
+        operands: &[],
+        constructor: |_| Opcode::Noop,
+    },
+    InstructionInfo {
+        mnemonic: "addx",
+        cycles: 2,
+        operands: &[Operand::Value],
+        constructor: |args| Opcode::Addx(args.value()),
+    },
+    InstructionInfo {
+        mnemonic: "add",
+        cycles: 2,
+        operands: &[Operand::Register, Operand::Value],
+        constructor: |args| Opcode::Add(args.register(), args.value()),
+    },
+    InstructionInfo {
+        mnemonic: "set",
+        cycles: 1,
+        operands: &[Operand::Register, Operand::Value],
+        constructor: |args| Opcode::Set(args.register(), args.value()),
+    },
+    InstructionInfo {
+        mnemonic: "jmp",
+        cycles: 1,
+        operands: &[Operand::Target],
+        constructor: |args| Opcode::Jmp(args.target()),
+    },
+    InstructionInfo {
+        mnemonic: "jz",
+        cycles: 2,
+        operands: &[Operand::Register, Operand::Target],
+        constructor: |args| Opcode::Jz(args.register(), args.target()),
+    },
+    InstructionInfo {
+        mnemonic: "jnz",
+        cycles: 2,
+        operands: &[Operand::Register, Operand::Target],
+        constructor: |args| Opcode::Jnz(args.register(), args.target()),
+    },
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Opcode {
     Noop,
     Addx(i32),
+    Add(Register, i32),
+    Set(Register, i32),
+    Jmp(usize),
+    Jz(Register, usize),
+    Jnz(Register, usize),
 }
 
 impl Opcode {
-    fn parse(input: &str) -> Self {
-        let tokens: Vec<&str> = input.splitn(2, ' ').collect();
-        match tokens[0] {
-            "noop" => Self::Noop,
-            "addx" => Self::Addx(tokens[1].parse().unwrap()),
-            instr => panic!("Unknown instruction {instr}"),
+    pub fn mnemonic(&self) -> &'static str {
+        self.info().mnemonic
+    }
+
+    /// The instruction's entry in the [INSTRUCTION_SET]
+    pub fn info(&self) -> &'static InstructionInfo {
+        let variant = std::mem::discriminant(self);
+        INSTRUCTION_SET
+            .iter()
+            .find(|info| std::mem::discriminant(&info.example()) == variant)
+            .expect("every opcode is in the instruction set")
+    }
+
+    /// How many cycles the instruction takes to finish
+    pub fn cycles(&self) -> usize {
+        self.info().cycles
+    }
+
+    /// The instruction's arguments, in the order they're written
+    pub fn arguments(&self) -> Vec<Argument> {
+        match *self {
+            Self::Noop => vec![],
+            Self::Addx(value) => vec![Argument::Value(value)],
+            Self::Add(register, value) | Self::Set(register, value) => {
+                vec![Argument::Register(register), Argument::Value(value)]
+            },
+            Self::Jmp(target) => vec![Argument::Target(target)],
+            Self::Jz(register, target) | Self::Jnz(register, target) => {
+                vec![Argument::Register(register), Argument::Target(target)]
+            },
+        }
+    }
+
+    /// Where the instruction might jump to, if it's a jump
+    pub fn target(&self) -> Option<usize> {
+        self.arguments()
+            .into_iter()
+            .find_map(|arg| match arg {
+                Argument::Target(target) => Some(target),
+                _ => None,
+            })
+    }
+
+    /// Write the instruction out, using a label for its jump target if
+    /// there is one
+    fn write_with_labels(&self, f: &mut std::fmt::Formatter<'_>, labels: &HashMap<usize, String>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+
+        for arg in self.arguments() {
+            match arg {
+                Argument::Register(register) => write!(f, " {register}")?,
+                Argument::Value(value) => write!(f, " {value}")?,
+                Argument::Target(target) => match labels.get(&target) {
+                    Some(label) => write!(f, " {label}")?,
+                    None => write!(f, " {target}")?,
+                },
+            }
         }
+
+        Ok(())
     }
 }
 
-pub struct Cpu {
-    x: i32,
-    pipeline: HashMap<i32, i32>,
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_with_labels(f, &HashMap::new())
+    }
 }
 
-impl Cpu {
-    pub fn init() -> Self {
-        Self { x: 1, pipeline: Default::default() }
+/// Things that can be wrong with a line of assembly
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownInstruction(String),
+    MissingOperand(Operand),
+    UnexpectedOperand(String),
+    BadRegister(String),
+    BadValue(String),
+    BadLabel(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownInstruction(name) => write!(f, "unknown instruction {name:?}"),
+            Self::MissingOperand(operand) => write!(f, "missing {operand:?} operand"),
+            Self::UnexpectedOperand(operand) => write!(f, "unexpected operand {operand:?}"),
+            Self::BadRegister(name) => write!(f, "no such register {name:?}"),
+            Self::BadValue(value) => write!(f, "bad value {value:?}"),
+            Self::BadLabel(label) => write!(f, "bad label {label:?}"),
+            Self::UndefinedLabel(label) => write!(f, "label {label:?} is never defined"),
+            Self::DuplicateLabel(label) => write!(f, "label {label:?} is already defined"),
+        }
+    }
+}
+
+/// A syntax error in an assembly program
+pub type AsmError = LineError<AsmErrorKind>;
+
+/// Split a line into its label (if any) and the instruction after it,
+/// ignoring comments
+fn split_line(line: &str) -> (Option<&str>, &str) {
+    let line = line.split(';').next().unwrap_or_default();
+    match line.split_once(':') {
+        Some((label, rest)) => (Some(label.trim()), rest.trim()),
+        None => (None, line.trim()),
+    }
+}
+
+fn parse_instruction(text: &str, labels: &HashMap<&str, usize>) -> Result<Opcode, AsmErrorKind> {
+    let mut tokens = text.split_whitespace();
+    let mnemonic = tokens.next().unwrap_or_default();
+    let info = INSTRUCTION_SET
+        .iter()
+        .find(|info| info.mnemonic == mnemonic)
+        .ok_or_else(|| AsmErrorKind::UnknownInstruction(mnemonic.into()))?;
+
+    let mut args = vec![];
+    for &operand in info.operands {
+        let token = tokens.next().ok_or(AsmErrorKind::MissingOperand(operand))?;
+        args.push(match operand {
+            Operand::Register => Argument::Register(token.parse()?),
+            Operand::Value => Argument::Value(token.parse().map_err(|_| AsmErrorKind::BadValue(token.into()))?),
+            Operand::Target => match token.parse() {
+                Ok(address) => Argument::Target(address),
+                Err(_) => Argument::Target(
+                    *labels
+                        .get(token)
+                        .ok_or_else(|| AsmErrorKind::UndefinedLabel(token.into()))?,
+                ),
+            },
+        });
+    }
+
+    if let Some(extra) = tokens.next() {
+        return Err(AsmErrorKind::UnexpectedOperand(extra.into()));
+    }
+
+    // The arguments were parsed to match the table, so they always fit
+    Ok((info.constructor)(&mut Arguments(args.iter())))
+}
+
+/// Turn assembly source into a program. Each line holds at most one
+/// instruction, optionally preceded by a `label:`, and anything after a
+/// `;` is a comment. Jumps can go to a label or straight to an address
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let program = assemble("
+///     set y 3
+/// loop:
+///     addx 2      ; x += 2, three times
+///     add y -1
+///     jnz y loop
+/// ").unwrap();
+///
+/// assert_eq!(program, vec![
+///     Opcode::Set(Register::Y, 3),
+///     Opcode::Addx(2),
+///     Opcode::Add(Register::Y, -1),
+///     Opcode::Jnz(Register::Y, 1),
+/// ]);
+///
+/// let mut cpu = Cpu::new(program);
+/// assert_eq!(cpu.run(), Stop::Halted);
+/// assert_eq!((cpu.x(), cpu.register(Register::Y)), (7, 0));
+///
+/// assert_eq!(
+///     assemble("noop\njmp nowhere"),
+///     Err(AsmError { line: 2, kind: AsmErrorKind::UndefinedLabel("nowhere".into()) }),
+/// );
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<Opcode>, AsmError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, split_line(line)))
+        .collect::<Vec<_>>();
+
+    // First pass works out where each label points
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for &(line, (label, text)) in &lines {
+        if let Some(label) = label {
+            let error = |kind| AsmError { line, kind };
+            if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') || label.parse::<usize>().is_ok() {
+                return Err(error(AsmErrorKind::BadLabel(label.into())));
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.into())));
+            }
+        }
+
+        if !text.is_empty() {
+            address += 1;
+        }
     }
+
+    lines
+        .iter()
+        .filter(|(_, (_, text))| !text.is_empty())
+        .map(|&(line, (_, text))| parse_instruction(text, &labels).map_err(|kind| AsmError { line, kind }))
+        .collect()
 }
 
-pub fn exec(source_code: &[&str]) -> Vec<i32> {
-    // Compile the source
-    let mut compiled = source_code
+/// Write a program back out as assembly that [assemble] will accept.
+/// Every jump target within the program gets a label
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let program = vec![
+///     Opcode::Set(Register::Z, 2),
+///     Opcode::Add(Register::Z, -1),
+///     Opcode::Jz(Register::Z, 4),
+///     Opcode::Jmp(1),
+///     Opcode::Noop,
+/// ];
+///
+/// let source = disassemble(&program);
+/// assert_eq!(source, "    set z 2\nL0:\n    add z -1\n    jz z L1\n    jmp L0\nL1:\n    noop\n");
+/// assert_eq!(assemble(&source), Ok(program));
+/// ```
+pub fn disassemble(program: &[Opcode]) -> String {
+    struct Line<'a>(&'a Opcode, &'a HashMap<usize, String>);
+
+    impl Display for Line<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.write_with_labels(f, self.1)
+        }
+    }
+
+    // Jumping to just past the end is a perfectly good way to halt, so
+    // that gets a label too
+    let labels = program
         .iter()
-        .map(|&x| Opcode::parse(x))
-        .fuse();
-
-    let mut cpu = Cpu::init();
-    let mut signal_strengths = vec![];
-    let mut crt = vec![];
-
-    for cycle in 1.. {
-        // During the cycle, emit the current signal strength if
-        // it's time for that
-        if [20, 60, 100, 140, 180, 220].contains(&cycle) {
-            signal_strengths.push(cycle * cpu.x);
-        }
-
-        // Update the CRT. The X register controls the current position
-        // of the center of the sprite
-        if i32::abs((cycle - 1) % 40 - cpu.x) <= 1 {
-            crt.push('#');
-        } else {
-            crt.push('.');
-        }
-
-        // Cycle has finished. See if there are any pending instructions for this
-        // cycle and execute them
-        if let Some(pending) = cpu.pipeline.remove(&cycle) {
-            cpu.x += pending;
-        } else if let Some(next_instr) = compiled.next() {
-            if let Opcode::Addx(pending) = next_instr {
-                cpu.pipeline.insert(cycle + 1, pending);
+        .filter_map(Opcode::target)
+        .filter(|&target| target <= program.len())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, target)| (target, format!("L{idx}")))
+        .collect::<HashMap<_, _>>();
+
+    let mut source = String::new();
+    for address in 0..=program.len() {
+        if let Some(label) = labels.get(&address) {
+            source += &format!("{label}:\n");
+        }
+        if let Some(instruction) = program.get(address) {
+            source += &format!("    {}\n", Line(instruction, &labels));
+        }
+    }
+
+    source
+}
+
+/// The cycles the puzzle asks for signal strengths at
+pub const SAMPLE_CYCLES: [usize; 6] = [20, 60, 100, 140, 180, 220];
+
+/// What the CPU was doing during a single cycle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tick {
+    pub cycle: usize,
+
+    /// The value of the X register during the cycle. Instructions only
+    /// change it once they finish, at the end of their last cycle
+    pub x: i32,
+
+    /// Where the instruction is in the program
+    pub address: usize,
+    pub instruction: Opcode,
+}
+
+impl Tick {
+    /// The cycle number times X. Worked out in 64 bits, as it can easily
+    /// be too big for X's 32
+    pub fn signal_strength(&self) -> i64 {
+        self.cycle as i64 * self.x as i64
+    }
+}
+
+/// Conditions that pause [Cpu::run]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Just before the given cycle starts
+    Cycle(usize),
+
+    /// As soon as the register is set to the given value
+    Value(Register, i32),
+
+    /// Whenever the register changes
+    Change(Register),
+}
+
+/// Why [Cpu::run] stopped
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// The program has finished
+    Halted,
+    Breakpoint(Breakpoint),
+}
+
+/// The handheld device's CPU, which runs one cycle at a time. Iterating
+/// over it steps through the rest of the program, giving a trace of
+/// every cycle. Registers are 32 bits and wrap around if they overflow
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let mut cpu = Cpu::new(vec![Opcode::Noop, Opcode::Addx(3), Opcode::Addx(-5)]);
+/// assert_eq!((cpu.current_cycle(), cpu.x()), (1, 1));
+///
+/// cpu.step();
+/// cpu.step();
+/// assert_eq!((cpu.current_cycle(), cpu.x()), (3, 1));
+///
+/// let tick = cpu.step().unwrap();
+/// assert_eq!((tick.cycle, tick.x, tick.instruction), (3, 1, Opcode::Addx(3)));
+/// assert_eq!((cpu.current_cycle(), cpu.x()), (4, 4));
+///
+/// let trace = cpu.by_ref().map(|tick| tick.x).collect::<Vec<_>>();
+/// assert_eq!(trace, vec![4, 4]);
+/// assert_eq!(cpu.x(), -1);
+/// assert!(cpu.is_halted());
+///
+/// let mut cpu = Cpu::new(vec![Opcode::Set(Register::X, i32::MAX), Opcode::Addx(1)]);
+/// cpu.by_ref().for_each(drop);
+/// assert_eq!(cpu.x(), i32::MIN);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cpu {
+    program: Vec<Opcode>,
+    pc: usize,
+    cycle: usize,
+    registers: [i32; 3],
+
+    /// The instruction in progress, where it is, and how many cycles it
+    /// has left
+    current: Option<(usize, Opcode, usize)>,
+    breakpoints: Vec<Breakpoint>,
+
+    /// The cycle a [Breakpoint::Cycle] last paused [Cpu::run] at, so that
+    /// running again doesn't stop there straight away
+    paused_at: Option<usize>,
+}
+
+impl Cpu {
+    pub fn new(program: Vec<Opcode>) -> Self {
+        Self {
+            program,
+            pc: 0,
+            cycle: 1,
+            registers: [1, 0, 0],
+            current: None,
+            breakpoints: vec![],
+            paused_at: None,
+        }
+    }
+
+    /// The cycle that will run on the next step
+    pub fn current_cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// The current value of the X register
+    pub fn x(&self) -> i32 {
+        self.register(Register::X)
+    }
+
+    pub fn register(&self, register: Register) -> i32 {
+        self.registers[register as usize]
+    }
+
+    /// Whether every instruction has finished
+    pub fn is_halted(&self) -> bool {
+        self.current.is_none() && self.pc >= self.program.len()
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Run a single cycle, fetching the next instruction if the last one
+    /// has finished. Returns `None` once the program has halted
+    pub fn step(&mut self) -> Option<Tick> {
+        if self.current.is_none() {
+            let instruction = *self.program.get(self.pc)?;
+            self.current = Some((self.pc, instruction, instruction.cycles()));
+            self.pc += 1;
+        }
+
+        let x = self.x();
+        let (address, instruction, remaining) = self.current.as_mut()?;
+        let tick = Tick { cycle: self.cycle, x, address: *address, instruction: *instruction };
+
+        // Cycle has finished. If that was the instruction's last one, it
+        // takes effect now
+        *remaining = remaining.saturating_sub(1);
+        if *remaining == 0 {
+            let instruction = *instruction;
+            self.current = None;
+            self.execute(instruction);
+        }
+
+        self.cycle += 1;
+        Some(tick)
+    }
+
+    fn execute(&mut self, instruction: Opcode) {
+        match instruction {
+            Opcode::Noop => {},
+            Opcode::Addx(value) => self.add(Register::X, value),
+            Opcode::Add(register, value) => self.add(register, value),
+            Opcode::Set(register, value) => self.registers[register as usize] = value,
+            Opcode::Jmp(target) => self.pc = target,
+            Opcode::Jz(register, target) => if self.register(register) == 0 {
+                self.pc = target;
+            },
+            Opcode::Jnz(register, target) => if self.register(register) != 0 {
+                self.pc = target;
+            },
+        }
+    }
+
+    fn add(&mut self, register: Register, value: i32) {
+        let register = &mut self.registers[register as usize];
+        *register = register.wrapping_add(value);
+    }
+
+    /// Keep stepping until a breakpoint is hit or the program halts. Calling
+    /// it again carries on past the breakpoint that stopped it
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day10::*;
+    ///
+    /// let mut cpu = Cpu::new(vec![Opcode::Addx(2), Opcode::Noop, Opcode::Addx(-3), Opcode::Noop]);
+    /// cpu.add_breakpoint(Breakpoint::Cycle(4));
+    /// cpu.add_breakpoint(Breakpoint::Value(Register::X, 0));
+    ///
+    /// assert_eq!(cpu.run(), Stop::Breakpoint(Breakpoint::Cycle(4)));
+    /// assert_eq!((cpu.current_cycle(), cpu.x()), (4, 3));
+    ///
+    /// assert_eq!(cpu.run(), Stop::Breakpoint(Breakpoint::Value(Register::X, 0)));
+    /// assert_eq!((cpu.current_cycle(), cpu.x()), (6, 0));
+    ///
+    /// assert_eq!(cpu.run(), Stop::Halted);
+    ///
+    /// // Other registers can be watched as well
+    /// let mut cpu = Cpu::new(vec![Opcode::Addx(1), Opcode::Set(Register::Y, 5), Opcode::Noop]);
+    /// cpu.add_breakpoint(Breakpoint::Change(Register::Y));
+    /// assert_eq!(cpu.run(), Stop::Breakpoint(Breakpoint::Change(Register::Y)));
+    /// assert_eq!((cpu.current_cycle(), cpu.register(Register::Y)), (4, 5));
+    /// assert_eq!(cpu.run(), Stop::Halted);
+    ///
+    /// // Stopping before the very first cycle works too
+    /// let mut cpu = Cpu::new(vec![Opcode::Noop]);
+    /// cpu.add_breakpoint(Breakpoint::Cycle(1));
+    /// assert_eq!(cpu.run(), Stop::Breakpoint(Breakpoint::Cycle(1)));
+    /// assert_eq!(cpu.current_cycle(), 1);
+    /// assert_eq!(cpu.run(), Stop::Halted);
+    /// ```
+    pub fn run(&mut self) -> Stop {
+        loop {
+            // Cycle breakpoints are checked before the cycle runs, so that
+            // even the first one can be caught
+            if !self.is_halted() && self.paused_at != Some(self.cycle) {
+                let cycle = Breakpoint::Cycle(self.cycle);
+                if self.breakpoints.contains(&cycle) {
+                    self.paused_at = Some(self.cycle);
+                    return Stop::Breakpoint(cycle);
+                }
+            }
+
+            let before = self.registers;
+            if self.step().is_none() {
+                return Stop::Halted;
+            }
+
+            let hit = self.breakpoints.iter().find(|&&breakpoint| match breakpoint {
+                Breakpoint::Cycle(_) => false,
+                Breakpoint::Value(register, value) => {
+                    let now = self.register(register);
+                    now != before[register as usize] && now == value
+                },
+                Breakpoint::Change(register) => self.register(register) != before[register as usize],
+            });
+
+            if let Some(&breakpoint) = hit {
+                return Stop::Breakpoint(breakpoint);
             }
-        } else if cpu.pipeline.is_empty() {
-            println!("Stop after cycle {cycle}");
-            break;
         }
     }
+}
+
+impl Iterator for Cpu {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step()
+    }
+}
+
+/// Assemble a program given as separate lines, see [assemble]
+pub fn compile(source_code: &[&str]) -> Result<Vec<Opcode>, AsmError> {
+    assemble(&source_code.join("\n"))
+}
+
+/// Signal strengths during each of the sample cycles. Samples past the end
+/// of the program are left out, and programs that loop forever are only
+/// run until the last sample
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let program = compile(&["noop", "addx 3", "addx -5"]).unwrap();
+/// assert_eq!(signal_strengths(&program, &[2, 4, 5, 6]), vec![2, 16, 20]);
+///
+/// let forever = compile(&["start: addx 1", "jmp start"]).unwrap();
+/// assert_eq!(signal_strengths(&forever, &[3, 6]), vec![6, 18]);
+///
+/// let big = compile(&["set x 100000000", "noop"]).unwrap();
+/// assert_eq!(signal_strengths(&big, &[2]), vec![200_000_000]);
+/// let big = compile(&["set x 100000000", "start: jmp start"]).unwrap();
+/// assert_eq!(signal_strengths(&big, &[100]), vec![10_000_000_000]);
+/// ```
+pub fn signal_strengths(program: &[Opcode], samples: &[usize]) -> Vec<i64> {
+    let last = samples.iter().copied().max().unwrap_or_default();
+
+    Cpu::new(program.to_vec())
+        .take_while(|tick| tick.cycle <= last)
+        .filter(|tick| samples.contains(&tick.cycle))
+        .map(|tick| tick.signal_strength())
+        .collect()
+}
+
+/// How many pixels wide the CRT is
+pub const CRT_WIDTH: usize = 40;
+
+/// How many rows of pixels the CRT has
+pub const CRT_HEIGHT: usize = 6;
+
+/// A picture drawn on the CRT, one `bool` per pixel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    pub rows: Vec<Vec<bool>>,
+}
+
+impl Framebuffer {
+    pub fn is_lit(&self, row: usize, col: usize) -> bool {
+        self.rows
+            .get(row)
+            .and_then(|row| row.get(col))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Read the letters drawn on the screen, see [ocr]
+    pub fn read(&self) -> Result<String, UnknownGlyph> {
+        ocr(self)
+    }
+}
+
+impl std::fmt::Display for Framebuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.rows {
+            let line = row
+                .iter()
+                .map(|&lit| if lit { '#' } else { '.' })
+                .collect::<String>();
+            writeln!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
 
-    for row in 0..6 {
-        for col in 0..40 {
-            let idx = row * 40 + col;
-            print!("{}", crt[idx]);
+/// Run a program and see what it draws on the CRT. Each cycle draws one
+/// pixel, left to right and top to bottom, which is lit if it lines up
+/// with the 3 pixel wide sprite centred on the X register
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let screen = draw_crt(&compile(&["addx 15", "addx -11", "addx 6", "addx -3"]).unwrap());
+/// assert_eq!(screen.to_string().lines().next(), Some("##..##.."));
+/// ```
+pub fn draw_crt(program: &[Opcode]) -> Framebuffer {
+    let mut rows: Vec<Vec<bool>> = vec![];
+
+    for tick in Cpu::new(program.to_vec()).take(CRT_WIDTH * CRT_HEIGHT) {
+        let col = (tick.cycle - 1) % CRT_WIDTH;
+        if col == 0 {
+            rows.push(vec![]);
+        }
+
+        if let Some(row) = rows.last_mut() {
+            row.push((col as i64 - tick.x as i64).abs() <= 1);
         }
-        println!();
     }
 
-    signal_strengths
+    Framebuffer { rows }
+}
+
+/// How many pixels wide each letter is. There's a blank column between
+/// each one too
+const GLYPH_WIDTH: usize = 4;
+
+/// The letters the elves' devices draw, row by row
+const FONT: &[(char, &str)] = &[
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+/// A letter on the screen that isn't in the font
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    /// Which letter it was, counting from zero on the left
+    pub position: usize,
+}
+
+impl std::fmt::Display for UnknownGlyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unrecognised letter at position {}", self.position)
+    }
+}
+
+impl std::error::Error for UnknownGlyph {}
+
+/// Read the capital letters drawn on a screen in the standard 4x6 font.
+/// Letters are read left to right, with a blank column after each
+///
+/// # Examples
+/// ```
+/// use aoc2022::day10::*;
+///
+/// let rows = [
+///     "#..#..###.",
+///     "#..#...#..",
+///     "####...#..",
+///     "#..#...#..",
+///     "#..#...#..",
+///     "#..#..###.",
+/// ];
+/// let mut screen = Framebuffer {
+///     rows: rows.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect(),
+/// };
+/// assert_eq!(screen.read(), Ok("HI".into()));
+///
+/// screen.rows[0][5] = true;
+/// assert_eq!(screen.read(), Err(UnknownGlyph { position: 1 }));
+/// ```
+pub fn ocr(screen: &Framebuffer) -> Result<String, UnknownGlyph> {
+    let width = screen.rows.iter().map(Vec::len).max().unwrap_or(0);
+    let letters = width.div_ceil(GLYPH_WIDTH + 1);
+
+    (0..letters)
+        .map(|position| {
+            let left = position * (GLYPH_WIDTH + 1);
+            let pattern = (0..CRT_HEIGHT)
+                .flat_map(|row| (left..left + GLYPH_WIDTH).map(move |col| (row, col)))
+                .map(|(row, col)| if screen.is_lit(row, col) { '#' } else { '.' })
+                .collect::<String>();
+
+            FONT.iter()
+                .find(|(_, glyph)| *glyph == pattern)
+                .map(|&(letter, _)| letter)
+                .ok_or(UnknownGlyph { position })
+        })
+        .collect()
+}
+
+pub fn exec(source_code: &[&str], samples: &[usize]) -> Result<Vec<i64>, AsmError> {
+    Ok(signal_strengths(&compile(source_code)?, samples))
 }
 
 #[cfg(test)]
@@ -87,7 +917,8 @@ mod answers {
 
     #[test]
     fn test_example_1() {
-        let total: i32 = exec(EXAMPLE1)
+        let total: i64 = exec(EXAMPLE1, &SAMPLE_CYCLES)
+            .unwrap()
             .iter()
             .sum();
         assert_eq!(total, 13140);
@@ -98,12 +929,28 @@ mod answers {
         let input = include_str!("./input/day10.txt")
             .lines()
             .collect::<Vec<_>>();
-        let total: i32 = exec(&input)
+        let total: i64 = exec(&input, &SAMPLE_CYCLES)
+            .unwrap()
             .iter()
             .sum();
         assert_eq!(total, 12560);
     }
 
+    #[test]
+    fn problem_2() {
+        let input = include_str!("./input/day10.txt")
+            .lines()
+            .collect::<Vec<_>>();
+        let screen = draw_crt(&compile(&input).unwrap());
+        assert_eq!(screen.read(), Ok("PLPAFBCL".into()));
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = assemble(include_str!("./input/day10.txt")).unwrap();
+        assert_eq!(assemble(&disassemble(&program)), Ok(program));
+    }
+
     const EXAMPLE1: &[&str] = &[
         "addx 15",
         "addx -11",
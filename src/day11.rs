@@ -1,17 +1,114 @@
 //! Day 11 - Monkey business
 
-#[derive(Debug)]
+use crate::line_error::LineError;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// One side of an [Operation]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// The item's current worry level
+    Old,
+    Num(u64),
+}
+
+impl Operand {
+    pub fn value(&self, old: u64) -> u128 {
+        match *self {
+            Self::Old => old as u128,
+            Self::Num(num) => num as u128,
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Old => write!(f, "old"),
+            Self::Num(num) => write!(f, "{num}"),
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = ParseErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "old" => Ok(Self::Old),
+            num => num
+                .parse()
+                .map(Self::Num)
+                .map_err(|_| ParseErrorKind::BadNumber(num.into())),
+        }
+    }
+}
+
+/// How a monkey changes an item's worry level when it inspects it
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let operation: Operation = "new = old * old".parse().unwrap();
+/// assert_eq!(operation, Operation::Mul(Operand::Old, Operand::Old));
+/// assert_eq!(operation.apply(7), 49);
+/// assert_eq!(Operation::Add(Operand::Old, Operand::Num(6)).to_string(), "new = old + 6");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add(Operand, Operand),
+    Mul(Operand, Operand),
+}
+
+impl Operation {
+    /// Work out the new worry level. Done in 128 bits, which is always big
+    /// enough to add or multiply two 64 bit numbers
+    pub fn apply(&self, old: u64) -> u128 {
+        match self {
+            Self::Add(lhs, rhs) => lhs.value(old) + rhs.value(old),
+            Self::Mul(lhs, rhs) => lhs.value(old) * rhs.value(old),
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Add(lhs, rhs) => write!(f, "new = {lhs} + {rhs}"),
+            Self::Mul(lhs, rhs) => write!(f, "new = {lhs} * {rhs}"),
+        }
+    }
+}
+
+impl FromStr for Operation {
+    type Err = ParseErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || ParseErrorKind::BadOperation(s.into());
+        let tokens = s.split_whitespace().collect::<Vec<_>>();
+
+        match tokens[..] {
+            ["new", "=", lhs, "+", rhs] => Ok(Self::Add(lhs.parse()?, rhs.parse()?)),
+            ["new", "=", lhs, "*", rhs] => Ok(Self::Mul(lhs.parse()?, rhs.parse()?)),
+            _ => Err(bad()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Monkey {
-    items: Vec<u64>,
-    operation: fn(u64) -> u64,
-    quotient: u64,
-    targets: (usize, usize),
-    inspected: usize,
+    pub items: Vec<u64>,
+    pub operation: Operation,
+    pub quotient: u64,
+    pub targets: (usize, usize),
+    pub inspected: usize,
 }
 
 impl Monkey {
-    pub fn do_operation(&self, worry_level: u64) -> u64 {
-        (self.operation)(worry_level)
+    pub fn do_operation(&self, worry_level: u64) -> u128 {
+        self.operation.apply(worry_level)
     }
 
     pub fn do_test(&self, worry_level: u64) -> usize {
@@ -23,116 +120,238 @@ impl Monkey {
     }
 }
 
-pub fn sample_monkeys() -> Vec<Monkey> {
-    vec![
-        Monkey {
-            items: vec![79, 98],
-            operation: |x| x * 19,
-            quotient: 23,
-            targets: (2, 3),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![54, 65, 75, 74],
-            operation: |x| x + 6,
-            quotient: 19,
-            targets: (2, 0),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![79, 60, 97],
-            operation: |x| x * x,
-            quotient: 13,
-            targets: (1, 3),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![74],
-            operation: |x| x + 3,
-            quotient: 17,
-            targets: (0, 1),
-            inspected: 0,
+/// Things that can be wrong with a line of the monkey notes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The line doesn't start the way it should. Holds what was expected
+    Expected(&'static str),
+    BadNumber(String),
+    BadOperation(String),
+
+    /// Monkeys have to be listed in order, starting from zero
+    MonkeyNumber { expected: usize, found: usize },
+
+    /// A monkey throws to a monkey that isn't in the notes
+    UnknownTarget(usize),
+
+    /// Nothing is divisible by zero
+    ZeroDivisor,
+
+    /// The notes stop part way through a monkey
+    UnexpectedEnd,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expected(prefix) => write!(f, "expected a line starting {prefix:?}"),
+            Self::BadNumber(num) => write!(f, "bad number {num:?}"),
+            Self::BadOperation(operation) => write!(f, "can't understand operation {operation:?}"),
+            Self::MonkeyNumber { expected, found } => write!(f, "expected monkey {expected}, found monkey {found}"),
+            Self::UnknownTarget(target) => write!(f, "there is no monkey {target} to throw to"),
+            Self::ZeroDivisor => write!(f, "the test can't be divisibility by zero"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of notes"),
         }
-    ]
+    }
 }
 
-pub fn personal_monkeys() -> Vec<Monkey> {
-    vec![
-        Monkey {
-            items: vec![63, 57],
-            operation: |x| x * 11,
-            quotient: 7,
-            targets: (6, 2),
-            inspected: 0
-        },
-        Monkey {
-            items: vec![82, 66, 87, 78, 77, 92, 83],
-            operation: |x| x + 1,
-            quotient: 11,
-            targets: (5, 0),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![97, 53, 53, 85, 58, 54],
-            operation: |x| x * 7,
-            quotient: 13,
-            targets: (4, 3),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![50],
-            operation: |x| x + 3,
-            quotient: 3,
-            targets: (1, 7),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![64, 69, 52, 65, 73],
-            operation: |x| x + 6,
-            quotient: 17,
-            targets: (3, 7),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![57, 91, 65],
-            operation: |x| x + 5,
-            quotient: 2,
-            targets: (0, 6),
-            inspected: 0,
-        },
-        Monkey {
-            items: vec![67, 91, 84, 78, 60, 69, 99, 83],
-            operation: |x| x * x,
-            quotient: 5,
-            targets: (2, 4),
-            inspected: 0,
+/// A problem with the notes
+pub type ParseError = LineError<ParseErrorKind>;
+
+fn parse_number<T: FromStr>(text: &str) -> Result<T, ParseErrorKind> {
+    let text = text.trim();
+    text.parse().map_err(|_| ParseErrorKind::BadNumber(text.into()))
+}
+
+/// Parse the notes on how each monkey behaves, in the format the puzzle
+/// gives them
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let monkeys = parse_monkeys("
+/// Monkey 0:
+///   Starting items: 79, 98
+///   Operation: new = old * 19
+///   Test: divisible by 23
+///     If true: throw to monkey 1
+///     If false: throw to monkey 1
+///
+/// Monkey 1:
+///   Starting items:
+///   Operation: new = old + old
+///   Test: divisible by 2
+///     If true: throw to monkey 0
+///     If false: throw to monkey 0
+/// ").unwrap();
+///
+/// assert_eq!(monkeys[0].items, vec![79, 98]);
+/// assert_eq!(monkeys[1].operation.to_string(), "new = old + old");
+///
+/// assert_eq!(
+///     parse_monkeys("Monkey 0:\n  Starting items: 1\n  Operation: new = old / 2"),
+///     Err(ParseError { line: 3, kind: ParseErrorKind::BadOperation("new = old / 2".into()) }),
+/// );
+/// ```
+pub fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .peekable();
+
+    let mut monkeys = vec![];
+    let mut targets = vec![];
+    let mut last_line = 0;
+
+    // Pull the next line, check it starts the right way, and hand back
+    // the rest of it along with its line number
+    let mut expect = |prefix: &'static str| match lines.next() {
+        Some((line, text)) => {
+            last_line = line;
+            text.strip_prefix(prefix)
+                .map(|rest| (line, rest))
+                .ok_or(ParseError { line, kind: ParseErrorKind::Expected(prefix) })
         },
-        Monkey {
-            items: vec![58, 78, 69, 65],
-            operation: |x| x + 7,
-            quotient: 19,
-            targets: (5, 1),
+        None => Err(ParseError { line: last_line, kind: ParseErrorKind::UnexpectedEnd }),
+    };
+
+    loop {
+        let (line, header) = match expect("Monkey ") {
+            Err(ParseError { kind: ParseErrorKind::UnexpectedEnd, .. }) if !monkeys.is_empty() => break,
+            other => other?,
+        };
+        let error = |kind| ParseError { line, kind };
+
+        let number = header.strip_suffix(':').ok_or(error(ParseErrorKind::Expected("Monkey N:")))?;
+        let number: usize = parse_number(number).map_err(error)?;
+        if number != monkeys.len() {
+            return Err(error(ParseErrorKind::MonkeyNumber { expected: monkeys.len(), found: number }));
+        }
+
+        let (line, items) = expect("Starting items:")?;
+        let items = items
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(parse_number)
+            .collect::<Result<_, _>>()
+            .map_err(|kind| ParseError { line, kind })?;
+
+        let (line, operation) = expect("Operation:")?;
+        let operation = operation.trim().parse().map_err(|kind| ParseError { line, kind })?;
+
+        let (line, quotient) = expect("Test: divisible by")?;
+        let quotient = parse_number(quotient).map_err(|kind| ParseError { line, kind })?;
+        if quotient == 0 {
+            return Err(ParseError { line, kind: ParseErrorKind::ZeroDivisor });
+        }
+
+        let (if_true_line, if_true) = expect("If true: throw to monkey")?;
+        let if_true = parse_number(if_true).map_err(|kind| ParseError { line: if_true_line, kind })?;
+        let (if_false_line, if_false) = expect("If false: throw to monkey")?;
+        let if_false = parse_number(if_false).map_err(|kind| ParseError { line: if_false_line, kind })?;
+
+        targets.push([(if_true_line, if_true), (if_false_line, if_false)]);
+        monkeys.push(Monkey {
+            items,
+            operation,
+            quotient,
+            targets: (if_true, if_false),
             inspected: 0,
+        });
+    }
+
+    // Can only check where the monkeys throw to once they've all been seen
+    for &(line, target) in targets.iter().flatten() {
+        if target >= monkeys.len() {
+            return Err(ParseError { line, kind: ParseErrorKind::UnknownTarget(target) });
         }
-    ]
+    }
+
+    Ok(monkeys)
 }
 
-pub fn simulate_round(monkeys: &mut Vec<Monkey>, relief: u64) {
-    let num_monkeys = monkeys.len();
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
 
-    // All the moduli are primes, so LCD is easy to figure out :)
-    let subring_quotient: u64 = monkeys
+/// The smallest number every monkey's divisor divides. Worry levels can be
+/// reduced modulo this without changing where any item gets thrown. Gives
+/// `None` if it doesn't fit in 64 bits
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let monkeys = parse_monkeys("
+/// Monkey 0:
+///   Starting items: 1
+///   Operation: new = old * old
+///   Test: divisible by 4
+///     If true: throw to monkey 1
+///     If false: throw to monkey 1
+///
+/// Monkey 1:
+///   Starting items: 2
+///   Operation: new = old + 3
+///   Test: divisible by 6
+///     If true: throw to monkey 0
+///     If false: throw to monkey 0
+/// ").unwrap();
+///
+/// assert_eq!(worry_modulus(&monkeys), Some(12));
+/// ```
+pub fn worry_modulus(monkeys: &[Monkey]) -> Option<u64> {
+    monkeys
         .iter()
-        .map(|m| m.quotient)
-        .product();
+        .try_fold(1u64, |lcm, monkey| (lcm / gcd(lcm, monkey.quotient)).checked_mul(monkey.quotient))
+}
+
+/// Relief that divides the worry level, like the puzzle's part 1
+pub fn divide_by(divisor: u128) -> impl Fn(u128) -> u128 {
+    move |level| level / divisor
+}
+
+/// No relief at all, like the puzzle's part 2
+pub fn no_relief(level: u128) -> u128 {
+    level
+}
+
+/// An item's worry level got too big to keep track of. Only possible when
+/// the monkeys' divisors have no common multiple that fits in 64 bits
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WorryOverflow {
+    /// Which monkey was inspecting the item
+    pub monkey: usize,
+    pub level: u128,
+}
+
+impl Display for WorryOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "monkey {} pushed a worry level to {}, which is too big", self.monkey, self.level)
+    }
+}
 
-    for idx in 0..num_monkeys {
+impl std::error::Error for WorryOverflow {}
+
+/// Let every monkey inspect and throw all of its items, in turn. After each
+/// inspection, `relief` is applied and then the worry level is reduced
+/// modulo [worry_modulus]. The reduction only keeps every throw exactly the
+/// same as an unreduced simulation with [no_relief], since dividing a
+/// reduced level can round differently
+pub fn simulate_round(monkeys: &mut [Monkey], relief: impl Fn(u128) -> u128) -> Result<(), WorryOverflow> {
+    let modulus = worry_modulus(monkeys);
+
+    for idx in 0..monkeys.len() {
         let mut throws = vec![];
 
         for item in &monkeys[idx].items {
-            let new_level = (monkeys[idx].do_operation(*item) / relief) % subring_quotient;
-            let new_target = monkeys[idx].do_test(new_level);
-            throws.push((new_level, new_target));
+            throws.push(inspect(monkeys, idx, *item, &relief, modulus)?);
         }
 
         monkeys[idx].inspected += monkeys[idx].items.len();
@@ -142,16 +361,324 @@ pub fn simulate_round(monkeys: &mut Vec<Monkey>, relief: u64) {
             monkeys[y].items.push(x);
         }
     }
+
+    Ok(())
+}
+
+/// A single monkey inspecting a single item. Gives the item's new worry
+/// level and which monkey it gets thrown to
+fn inspect(
+    monkeys: &[Monkey],
+    idx: usize,
+    level: u64,
+    relief: &impl Fn(u128) -> u128,
+    modulus: Option<u64>,
+) -> Result<(u64, usize), WorryOverflow> {
+    let mut new_level = relief(monkeys[idx].do_operation(level));
+    if let Some(modulus) = modulus {
+        new_level %= modulus as u128;
+    }
+
+    let new_level = u64::try_from(new_level)
+        .map_err(|_| WorryOverflow { monkey: idx, level: new_level })?;
+    Ok((new_level, monkeys[idx].do_test(new_level)))
+}
+
+/// Every item the monkeys start with, as the monkey holding it and its worry
+/// level. Items are numbered in this order - monkey by monkey, and then in
+/// the order each monkey holds them
+pub fn starting_items(monkeys: &[Monkey]) -> Vec<(usize, u64)> {
+    monkeys
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, monkey)| monkey.items.iter().map(move |&level| (idx, level)))
+        .collect()
+}
+
+/// What happened during a single round, see [track_items]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundStats {
+    /// How many items each monkey inspected
+    pub inspections: Vec<usize>,
+
+    /// Which monkey is holding each item once the round is over
+    pub holders: Vec<usize>,
+}
+
+/// Run the monkeys for a number of rounds, following each item as it's
+/// thrown around. Items are numbered as in [starting_items]. The monkeys
+/// themselves are left untouched
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let monkeys = parse_monkeys("
+/// Monkey 0:
+///   Starting items: 1, 2
+///   Operation: new = old + 1
+///   Test: divisible by 2
+///     If true: throw to monkey 1
+///     If false: throw to monkey 0
+///
+/// Monkey 1:
+///   Starting items:
+///   Operation: new = old + 1
+///   Test: divisible by 2
+///     If true: throw to monkey 1
+///     If false: throw to monkey 0
+/// ").unwrap();
+///
+/// let rounds = track_items(&monkeys, 2, no_relief).unwrap();
+///
+/// // Item 0 goes straight on to monkey 1, who throws it back
+/// assert_eq!(rounds[0].inspections, vec![2, 1]);
+/// assert_eq!(rounds[0].holders, vec![0, 0]);
+/// assert_eq!(rounds[1].inspections, vec![2, 2]);
+/// assert_eq!(rounds[1].holders, vec![0, 0]);
+/// ```
+pub fn track_items(
+    monkeys: &[Monkey],
+    rounds: usize,
+    relief: impl Fn(u128) -> u128,
+) -> Result<Vec<RoundStats>, WorryOverflow> {
+    let modulus = worry_modulus(monkeys);
+    let items = starting_items(monkeys);
+    let mut holders = items.iter().map(|&(holder, _)| holder).collect::<Vec<_>>();
+
+    // Each monkey's items, as item numbers and worry levels
+    let mut queues = vec![vec![]; monkeys.len()];
+    for (id, &(holder, level)) in items.iter().enumerate() {
+        queues[holder].push((id, level));
+    }
+
+    let mut stats = vec![];
+    for _ in 0..rounds {
+        let mut inspections = vec![0; monkeys.len()];
+
+        for idx in 0..monkeys.len() {
+            for (id, level) in std::mem::take(&mut queues[idx]) {
+                let (new_level, target) = inspect(monkeys, idx, level, &relief, modulus)?;
+                inspections[idx] += 1;
+                holders[id] = target;
+                queues[target].push((id, new_level));
+            }
+        }
+
+        stats.push(RoundStats { inspections, holders: holders.clone() });
+    }
+
+    Ok(stats)
+}
+
+/// When an item's journey starts repeating itself
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ItemCycle {
+    /// How many rounds go by before the repeating starts
+    pub start: usize,
+
+    /// How many rounds it takes to end up with the same monkey and the same
+    /// worry level again
+    pub period: usize,
+}
+
+/// How many rounds of an item's journey are remembered while looking for
+/// it to repeat itself. Past this, rounds are only counted
+pub const HISTORY_LIMIT: usize = 100_000;
+
+/// A single item's journey, round by round
+struct Journey {
+    /// Which monkeys inspected the item during each of the first
+    /// [HISTORY_LIMIT] rounds
+    visits: Vec<Vec<usize>>,
+
+    /// How many times each monkey inspected the item after that
+    unrecorded: Vec<usize>,
+    cycle: Option<ItemCycle>,
+}
+
+/// Follow a single item for up to `rounds` rounds, stopping early if it
+/// ends a round in the same state as it ended an earlier one. Items don't
+/// affect each other, so this works just as well as simulating everything.
+/// Only the first [HISTORY_LIMIT] rounds are kept, so memory stays bounded
+/// however many rounds there are
+fn follow_item(
+    monkeys: &[Monkey],
+    (mut holder, mut level): (usize, u64),
+    rounds: usize,
+    relief: &impl Fn(u128) -> u128,
+    modulus: Option<u64>,
+) -> Result<Journey, WorryOverflow> {
+    let mut seen = HashMap::from([((holder, level), 0)]);
+    let mut visits = vec![];
+    let mut unrecorded = vec![0; monkeys.len()];
+
+    for round in 1..=rounds {
+        // Items thrown to a later monkey get inspected again in the same round
+        let mut visited = vec![];
+        loop {
+            visited.push(holder);
+            let (new_level, target) = inspect(monkeys, holder, level, relief, modulus)?;
+            level = new_level;
+
+            let later = target > holder;
+            holder = target;
+            if !later {
+                break;
+            }
+        }
+
+        if round > HISTORY_LIMIT {
+            for holder in visited {
+                unrecorded[holder] += 1;
+            }
+            continue;
+        }
+        visits.push(visited);
+
+        if let Some(start) = seen.insert((holder, level), round) {
+            let cycle = ItemCycle { start, period: round - start };
+            return Ok(Journey { visits, unrecorded, cycle: Some(cycle) });
+        }
+    }
+
+    Ok(Journey { visits, unrecorded, cycle: None })
+}
+
+/// Find out when an item (numbered as in [starting_items]) starts going
+/// round in circles, giving up after `limit` rounds or [HISTORY_LIMIT],
+/// whichever comes first. There's no cycle to find for an item that
+/// doesn't exist
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let monkeys = parse_monkeys("
+/// Monkey 0:
+///   Starting items: 2
+///   Operation: new = old * 2
+///   Test: divisible by 3
+///     If true: throw to monkey 0
+///     If false: throw to monkey 0
+/// ").unwrap();
+///
+/// // Worry levels go 2, 1, 2, ...
+/// assert_eq!(find_item_cycle(&monkeys, 0, no_relief, 10), Ok(Some(ItemCycle { start: 0, period: 2 })));
+/// assert_eq!(find_item_cycle(&monkeys, 0, no_relief, 1), Ok(None));
+/// assert_eq!(find_item_cycle(&monkeys, 1, no_relief, 10), Ok(None));
+/// ```
+pub fn find_item_cycle(
+    monkeys: &[Monkey],
+    item: usize,
+    relief: impl Fn(u128) -> u128,
+    limit: usize,
+) -> Result<Option<ItemCycle>, WorryOverflow> {
+    let Some(&start) = starting_items(monkeys).get(item) else {
+        return Ok(None);
+    };
+    let limit = limit.min(HISTORY_LIMIT);
+    Ok(follow_item(monkeys, start, limit, &relief, worry_modulus(monkeys))?.cycle)
+}
+
+/// How many items each monkey inspects over a number of rounds. Rather than
+/// simulating every round, each item is followed until it starts repeating
+/// itself and the rest is worked out from the cycle. Items that don't repeat
+/// within [HISTORY_LIMIT] rounds are simulated the rest of the way
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// let monkeys = parse_monkeys("
+/// Monkey 0:
+///   Starting items: 2, 5
+///   Operation: new = old + 1
+///   Test: divisible by 3
+///     If true: throw to monkey 1
+///     If false: throw to monkey 0
+///
+/// Monkey 1:
+///   Starting items:
+///   Operation: new = old + 1
+///   Test: divisible by 2
+///     If true: throw to monkey 0
+///     If false: throw to monkey 0
+/// ").unwrap();
+///
+/// // Same answer as doing it the slow way
+/// let simulated = track_items(&monkeys, 1000, no_relief)
+///     .unwrap()
+///     .iter()
+///     .fold(vec![0, 0], |total, round| vec![total[0] + round.inspections[0], total[1] + round.inspections[1]]);
+/// assert_eq!(extrapolate_inspections(&monkeys, 1000, no_relief).unwrap(), simulated);
+///
+/// let inspections = extrapolate_inspections(&monkeys, 1_000_000_000, no_relief).unwrap();
+/// assert_eq!(inspections, vec![2_000_000_000, 1_000_000_000]);
+/// ```
+pub fn extrapolate_inspections(
+    monkeys: &[Monkey],
+    rounds: usize,
+    relief: impl Fn(u128) -> u128,
+) -> Result<Vec<usize>, WorryOverflow> {
+    let modulus = worry_modulus(monkeys);
+    let mut inspections = vec![0; monkeys.len()];
+
+    for start in starting_items(monkeys) {
+        let journey = follow_item(monkeys, start, rounds, &relief, modulus)?;
+        let mut count = |rounds: &[Vec<usize>], times: usize| {
+            for &holder in rounds.iter().flatten() {
+                inspections[holder] += times;
+            }
+        };
+
+        match journey.cycle {
+            Some(ItemCycle { start, period }) => {
+                let cycle = &journey.visits[start..start + period];
+                let remaining = rounds - start;
+
+                count(&journey.visits[..start], 1);
+                count(cycle, remaining / period);
+                count(&cycle[..remaining % period], 1);
+            },
+            None => {
+                count(&journey.visits, 1);
+                for (total, extra) in inspections.iter_mut().zip(&journey.unrecorded) {
+                    *total += extra;
+                }
+            },
+        }
+    }
+
+    Ok(inspections)
+}
+
+/// The product of the two biggest inspection counts. With fewer than two
+/// monkeys, the missing ones count as inspecting nothing
+///
+/// # Examples
+/// ```
+/// use aoc2022::day11::*;
+///
+/// assert_eq!(monkey_business(&[3, 10, 4]), 40);
+/// assert_eq!(monkey_business(&[3]), 0);
+/// assert_eq!(monkey_business(&[]), 0);
+/// ```
+pub fn monkey_business(inspections: &[usize]) -> usize {
+    let mut activity_levels = inspections.to_vec();
+
+    activity_levels.sort_unstable_by(|a, b| b.cmp(a));
+    activity_levels.resize(activity_levels.len().max(2), 0);
+    activity_levels[0] * activity_levels[1]
 }
 
 pub fn calculate_monkey_business(monkeys: &[Monkey]) -> usize {
-    let mut activity_levels = monkeys
+    let activity_levels = monkeys
         .iter()
         .map(|monkey| monkey.inspected)
         .collect::<Vec<_>>();
 
-    activity_levels.sort_unstable_by(|a, b| b.partial_cmp(a).unwrap());
-    activity_levels[0] * activity_levels[1]
+    monkey_business(&activity_levels)
 }
 
 #[cfg(test)]
@@ -159,15 +686,69 @@ mod answers {
     use super::*;
     use test_case::test_case;
 
-    #[test_case(20, 3, sample_monkeys() => 10605; "sample data problem 1")]
-    #[test_case(20, 3, personal_monkeys() => 107822; "real data problem 1")]
-    #[test_case(10000, 1, sample_monkeys() => 2713310158; "sample data problem 2")]
-    #[test_case(10000, 1, personal_monkeys() => 27267163742; "real data problem 2")]
-    fn example_data(rounds: u32, relief: u64, mut monkeys: Vec<Monkey>) -> usize {
+    #[test_case(20, 3, SAMPLE => 10605; "sample data problem 1")]
+    #[test_case(20, 3, include_str!("./input/day11.txt") => 107822; "real data problem 1")]
+    #[test_case(10000, 1, SAMPLE => 2713310158; "sample data problem 2")]
+    #[test_case(10000, 1, include_str!("./input/day11.txt") => 27267163742; "real data problem 2")]
+    fn example_data(rounds: u32, relief: u128, notes: &str) -> usize {
+        let mut monkeys = parse_monkeys(notes).unwrap();
         for _ in 0..rounds {
-            simulate_round(&mut monkeys, relief);
+            simulate_round(&mut monkeys, divide_by(relief)).unwrap();
         }
 
         calculate_monkey_business(&monkeys)
     }
+
+    #[test_case(SAMPLE => 2713310158; "sample data")]
+    #[test_case(include_str!("./input/day11.txt") => 27267163742; "real data")]
+    fn extrapolated(notes: &str) -> usize {
+        let monkeys = parse_monkeys(notes).unwrap();
+        monkey_business(&extrapolate_inspections(&monkeys, 10000, no_relief).unwrap())
+    }
+
+    #[test]
+    fn tracking_matches_simulation() {
+        let mut monkeys = parse_monkeys(include_str!("./input/day11.txt")).unwrap();
+        let rounds = track_items(&monkeys, 100, no_relief).unwrap();
+
+        for stats in rounds {
+            let before = monkeys.iter().map(|monkey| monkey.inspected).collect::<Vec<_>>();
+            simulate_round(&mut monkeys, no_relief).unwrap();
+
+            for (idx, monkey) in monkeys.iter().enumerate() {
+                assert_eq!(monkey.inspected - before[idx], stats.inspections[idx]);
+                assert_eq!(monkey.items.len(), stats.holders.iter().filter(|&&holder| holder == idx).count());
+            }
+        }
+    }
+
+    const SAMPLE: &str = "\
+Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
 }
@@ -1,102 +1,452 @@
 //! Day 12 - Hill climbing algorithm
 
-use std::collections::{HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
 pub type HeightMap = Vec<Vec<u8>>;
 pub type Coordinate = (isize, isize);
 
-pub fn load_grid(lines: &[&str]) -> (HeightMap, Coordinate, Coordinate) {
+/// Ways a map can be broken
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GridError {
+    NoStart,
+    NoEnd,
+
+    /// A second `S`, and where it is
+    ExtraStart(Coordinate),
+
+    /// A second `E`, and where it is
+    ExtraEnd(Coordinate),
+
+    /// Something other than a lowercase letter, `S` or `E`
+    BadHeight(Coordinate, char),
+}
+
+impl std::fmt::Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoStart => write!(f, "the map has no start"),
+            Self::NoEnd => write!(f, "the map has no end"),
+            Self::ExtraStart((x, y)) => write!(f, "a second start at ({x}, {y})"),
+            Self::ExtraEnd((x, y)) => write!(f, "a second end at ({x}, {y})"),
+            Self::BadHeight((x, y), c) => write!(f, "{c:?} at ({x}, {y}) isn't a height"),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// Read a map, along with where the start and end are. There has to be
+/// exactly one of each
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let (grid, start, end) = load_grid(&["Sbc", "zyE"]).unwrap();
+/// assert_eq!(grid, vec![b"abc".to_vec(), b"zyz".to_vec()]);
+/// assert_eq!((start, end), ((0, 0), (2, 1)));
+///
+/// assert_eq!(load_grid(&["abc", "zyE"]), Err(GridError::NoStart));
+/// assert_eq!(load_grid(&["SbE", "zyE"]), Err(GridError::ExtraEnd((2, 1))));
+/// assert_eq!(load_grid(&["Sb?", "zyE"]), Err(GridError::BadHeight((2, 0), '?')));
+/// ```
+pub fn load_grid(lines: &[&str]) -> Result<(HeightMap, Coordinate, Coordinate), GridError> {
     let mut grid = vec![];
-    let mut start = (0, 0);
-    let mut end = (0, 0);
+    let mut start = None;
+    let mut end = None;
 
     for (u, row) in lines.iter().enumerate() {
-        let grid_col = row
-            .chars()
-            .enumerate()
-            .inspect(|(v, c)| {
-                if *c == 'S' {
-                    start = (*v as isize, u as isize);
-                } else if *c == 'E' {
-                    end = (*v as isize, u as isize);
-                }
-            })
-            .map(|(_, c)| {
-                match c {
-                    'S' => b'a',
-                    'E' => b'z',
-                    c => c as u8,
-                }
-            })
-            .collect();
+        let mut grid_col = vec![];
+
+        for (v, c) in row.chars().enumerate() {
+            let position = (v as isize, u as isize);
+            grid_col.push(match c {
+                'S' => {
+                    if start.replace(position).is_some() {
+                        return Err(GridError::ExtraStart(position));
+                    }
+                    b'a'
+                },
+                'E' => {
+                    if end.replace(position).is_some() {
+                        return Err(GridError::ExtraEnd(position));
+                    }
+                    b'z'
+                },
+                'a'..='z' => c as u8,
+                c => return Err(GridError::BadHeight(position, c)),
+            });
+        }
 
         grid.push(grid_col);
     }
 
-    (grid, start, end)
+    Ok((grid, start.ok_or(GridError::NoStart)?, end.ok_or(GridError::NoEnd)?))
+}
+
+/// How a hiker gets around. Each step goes to a neighbouring cell, as long
+/// as it doesn't climb or drop too far
+pub trait ClimbingRule {
+    /// The most a single step can go up
+    fn max_ascent(&self) -> u8;
+
+    /// The most a single step can go down
+    fn max_descent(&self) -> u8;
+
+    /// What it costs to step from one height to another
+    fn cost(&self, _from: u8, _to: u8) -> u32 {
+        1
+    }
+
+    fn can_step(&self, from: u8, to: u8) -> bool {
+        if to >= from {
+            to - from <= self.max_ascent()
+        } else {
+            from - to <= self.max_descent()
+        }
+    }
+}
+
+/// The puzzle's rules - climb at most one unit at a time, but drop as far
+/// as you like. Every step costs the same
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PuzzleRules;
+
+impl ClimbingRule for PuzzleRules {
+    fn max_ascent(&self) -> u8 {
+        1
+    }
+
+    fn max_descent(&self) -> u8 {
+        u8::MAX
+    }
+}
+
+/// A hiker who pays for every step, and extra for every unit of height
+/// they gain
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let hiker = Hiker { max_ascent: 2, max_descent: 1, step_cost: 1, climb_cost: 10 };
+/// assert!(hiker.can_step(b'a', b'c'));
+/// assert!(!hiker.can_step(b'c', b'a'));
+/// assert_eq!(hiker.cost(b'a', b'c'), 21);
+/// assert_eq!(hiker.cost(b'c', b'b'), 1);
+///
+/// // Costs top out rather than overflowing
+/// let hiker = Hiker { climb_cost: u32::MAX / 2, ..hiker };
+/// assert_eq!(hiker.cost(b'a', b'z'), u32::MAX);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Hiker {
+    pub max_ascent: u8,
+    pub max_descent: u8,
+    pub step_cost: u32,
+    pub climb_cost: u32,
+}
+
+impl ClimbingRule for Hiker {
+    fn max_ascent(&self) -> u8 {
+        self.max_ascent
+    }
+
+    fn max_descent(&self) -> u8 {
+        self.max_descent
+    }
+
+    fn cost(&self, from: u8, to: u8) -> u32 {
+        let climb = self.climb_cost.saturating_mul(to.saturating_sub(from) as u32);
+        self.step_cost.saturating_add(climb)
+    }
+}
+
+/// A route across the map, from start to end inclusive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    pub cells: Vec<Coordinate>,
+}
+
+impl Path {
+    /// How many steps it takes to walk
+    pub fn len(&self) -> u32 {
+        self.cells.len().saturating_sub(1) as u32
+    }
+
+    /// Whether the path goes nowhere, i.e. it starts at the end
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn start(&self) -> Option<Coordinate> {
+        self.cells.first().copied()
+    }
+
+    pub fn end(&self) -> Option<Coordinate> {
+        self.cells.last().copied()
+    }
 }
 
-pub fn do_bfs(grid: &Vec<Vec<u8>>, start: (isize, isize), end: (isize, isize)) -> u32 {
-    let mut queue = VecDeque::new();
-    let mut visited = HashSet::new();
+fn height(grid: &[Vec<u8>], (x, y): Coordinate) -> Option<u8> {
+    let row = grid.get(usize::try_from(y).ok()?)?;
+    row.get(usize::try_from(x).ok()?).copied()
+}
 
-    queue.push_back((0, start));
-    visited.insert(start);
+/// Every cell next to this one that's actually on the map
+fn neighbours(grid: &[Vec<u8>], (x, y): Coordinate) -> impl Iterator<Item = Coordinate> + '_ {
+    [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        .into_iter()
+        .filter(|&cell| height(grid, cell).is_some())
+}
 
-    while let Some((distance, current)) = queue.pop_front() {
-        let (cur_x, cur_y) = current;
-        for (x, y) in [(cur_x - 1, cur_y), (cur_x + 1, cur_y), (cur_x, cur_y - 1), (cur_x, cur_y + 1)] {
-            let ux = x as usize;
-            let uy = y as usize;
+/// Walk back along the breadcrumbs from a cell to wherever the search started
+fn trace_back(parents: &HashMap<Coordinate, Coordinate>, from: Coordinate) -> Vec<Coordinate> {
+    let mut cells = vec![from];
+    while let Some(&parent) = parents.get(cells.last().unwrap()) {
+        cells.push(parent);
+    }
+    cells
+}
 
-            if x >= 0 && y >= 0 && uy < grid.len() && ux < grid[uy].len() && grid[uy][ux] <= grid[cur_y as usize][cur_x as usize] + 1 && visited.insert((x, y)) {
-                if (x, y) == end {
-                    return distance + 1;
-                }
+/// Find the fewest steps from start to end with a breadth-first search.
+/// Returns `None` if the end can't be reached
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let (grid, start, end) = load_grid(&["Sbcd", "zzzE"]).unwrap();
+/// let path = shortest_path(&grid, start, (3, 0)).unwrap();
+/// assert_eq!(path.cells, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+///
+/// // Dropping down is fine, but the zs are too steep to climb
+/// assert_eq!(shortest_path(&grid, end, start).map(|path| path.len()), Some(4));
+/// assert_eq!(shortest_path(&grid, start, end), None);
+/// ```
+pub fn shortest_path(grid: &[Vec<u8>], start: Coordinate, end: Coordinate) -> Option<Path> {
+    let mut queue = VecDeque::from([start]);
+    let mut parents = HashMap::new();
+    let mut visited = HashSet::from([start]);
 
-                queue.push_back((distance + 1, (x, y)));              
+    while let Some(current) = queue.pop_front() {
+        if current == end {
+            let mut cells = trace_back(&parents, end);
+            cells.reverse();
+            return Some(Path { cells });
+        }
+
+        let current_height = height(grid, current)?;
+        for next in neighbours(grid, current) {
+            if PuzzleRules.can_step(current_height, height(grid, next)?) && visited.insert(next) {
+                parents.insert(next, current);
+                queue.push_back(next);
             }
         }
     }
 
-    0
+    None
 }
 
-#[cfg(test)]
-mod answers {
-    use super::*;
-    use test_case::test_case;
+/// Find the shortest path to the end from any cell of a given height. This
+/// searches backwards from the end, so it's a single search however many
+/// cells there are of that height
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let (grid, _, end) = load_grid(&[
+///     "Sabqponm",
+///     "abcryxxl",
+///     "accszExk",
+///     "acctuvwj",
+///     "abdefghi",
+/// ]).unwrap();
+/// let path = shortest_from_height(&grid, end, b'a').unwrap();
+///
+/// assert_eq!(path.start(), Some((0, 4)));
+/// assert_eq!(path.len(), 29);
+/// assert_eq!(shortest_from_height(&grid, end, b'f').map(|path| path.len()), Some(23));
+/// assert_eq!(shortest_from_height(&grid, end, b'q').map(|path| path.len()), Some(12));
+/// ```
+pub fn shortest_from_height(grid: &[Vec<u8>], end: Coordinate, target: u8) -> Option<Path> {
+    let mut queue = VecDeque::from([end]);
+    let mut parents = HashMap::new();
+    let mut visited = HashSet::from([end]);
+
+    while let Some(current) = queue.pop_front() {
+        let current_height = height(grid, current)?;
+        if current_height == target {
+            // The breadcrumbs point towards the end, so they already lead
+            // the right way
+            return Some(Path { cells: trace_back(&parents, current) });
+        }
 
-    #[test_case(SAMPLE_GRID => 31; "with example data")]
-    #[test_case(personal_grid().as_slice() => 534; "with personal data")]
-    fn problem1(input: &[&str]) -> u32 {
-        let (grid, start, end) = load_grid(input);
-        do_bfs(&grid, start, end)
-    }
-
-    #[test_case(SAMPLE_GRID => 29; "with example data")]
-    #[test_case(personal_grid().as_slice() => 525; "with personal data")]
-    fn problem2(input: &[&str]) -> u32 {
-        let (grid, _, end) = load_grid(input);
-        let mut shortest = u32::MAX;
-        let mut candidates = vec![];
-
-        for (i, line) in grid.iter().enumerate() {
-            for (j, k) in line.iter().enumerate() {
-                if *k == b'a' {
-                    candidates.push((j as isize, i as isize));
-                }
+        for prev in neighbours(grid, current) {
+            if PuzzleRules.can_step(height(grid, prev)?, current_height) && visited.insert(prev) {
+                parents.insert(prev, current);
+                queue.push_back(prev);
             }
         }
+    }
+
+    None
+}
+
+/// Find the cheapest way from start to end under any climbing rules, with
+/// Dijkstra's algorithm. Returns the path and what it costs, or `None` if
+/// the end can't be reached. Costs too big for a `u32` come out as
+/// `u32::MAX`
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let (grid, start, _) = load_grid(&[
+///     "Szzzb",
+///     "bcdcb",
+///     "zzzzE",
+/// ]).unwrap();
+///
+/// // The puzzle's rules have to go the long way round
+/// let (path, cost) = cheapest_path(&grid, start, (4, 0), &PuzzleRules).unwrap();
+/// assert_eq!((path.len(), cost), (6, 6));
+///
+/// // A strong climber goes straight over the top...
+/// let mut hiker = Hiker { max_ascent: 25, max_descent: 25, step_cost: 1, climb_cost: 0 };
+/// let (path, cost) = cheapest_path(&grid, start, (4, 0), &hiker).unwrap();
+/// assert_eq!((path.len(), cost), (4, 4));
+///
+/// // ...unless climbing costs extra
+/// hiker.climb_cost = 1;
+/// let (path, cost) = cheapest_path(&grid, start, (4, 0), &hiker).unwrap();
+/// assert_eq!((path.len(), cost), (6, 9));
+///
+/// // Someone who can't drop down at all never gets past the top row
+/// hiker.max_descent = 0;
+/// assert_eq!(cheapest_path(&grid, (1, 0), (1, 1), &hiker), None);
+///
+/// let hiker = Hiker { max_ascent: 25, max_descent: 25, step_cost: 1, climb_cost: u32::MAX / 2 };
+/// let (_, cost) = cheapest_path(&grid, start, (4, 0), &hiker).unwrap();
+/// assert_eq!(cost, u32::MAX);
+/// ```
+pub fn cheapest_path(grid: &[Vec<u8>], start: Coordinate, end: Coordinate, rule: &impl ClimbingRule) -> Option<(Path, u32)> {
+    let mut queue = BinaryHeap::from([Reverse((0, start))]);
+    let mut parents = HashMap::new();
+    let mut costs = HashMap::from([(start, 0)]);
+
+    while let Some(Reverse((cost, current))) = queue.pop() {
+        if current == end {
+            let mut cells = trace_back(&parents, end);
+            cells.reverse();
+            return Some((Path { cells }, cost));
+        }
+
+        // Stale entry, there's already a cheaper way here
+        if costs.get(&current).is_some_and(|&best| best < cost) {
+            continue;
+        }
 
-        for candidate in candidates {
-            let q = do_bfs(&grid, candidate, end);
-            if q > 0 {
-                shortest = shortest.min(q);
+        let current_height = height(grid, current)?;
+        for next in neighbours(grid, current) {
+            let next_height = height(grid, next)?;
+            if !rule.can_step(current_height, next_height) {
+                continue;
+            }
+
+            let next_cost = cost.saturating_add(rule.cost(current_height, next_height));
+            if costs.get(&next).map_or(true, |&best| next_cost < best) {
+                costs.insert(next, next_cost);
+                parents.insert(next, current);
+                queue.push(Reverse((next_cost, next)));
             }
         }
+    }
+
+    None
+}
+
+/// Draw a path over the map the way the puzzle does, with an arrow on each
+/// cell showing which way to go next
+///
+/// # Examples
+/// ```
+/// use aoc2022::day12::*;
+///
+/// let (grid, start, end) = load_grid(&[
+///     "Sabqponm",
+///     "abcryxxl",
+///     "accszExk",
+///     "acctuvwj",
+///     "abdefghi",
+/// ]).unwrap();
+/// let path = shortest_path(&grid, start, end).unwrap();
+///
+/// assert_eq!(path.len(), 31);
+/// assert_eq!(render_path(&grid, &path), "\
+/// >>vv<<<<
+/// ..vvv<<^
+/// ..vv>E^^
+/// ..v>>>^^
+/// ..>>>>>^
+/// ");
+/// ```
+pub fn render_path(grid: &[Vec<u8>], path: &Path) -> String {
+    let mut canvas = grid
+        .iter()
+        .map(|row| vec!['.'; row.len()])
+        .collect::<Vec<_>>();
+
+    for step in path.cells.windows(2) {
+        let ((x, y), (next_x, next_y)) = (step[0], step[1]);
+        canvas[y as usize][x as usize] = match (next_x - x, next_y - y) {
+            (1, _) => '>',
+            (-1, _) => '<',
+            (_, 1) => 'v',
+            _ => '^',
+        };
+    }
+
+    if let Some((x, y)) = path.end() {
+        canvas[y as usize][x as usize] = 'E';
+    }
+
+    canvas
+        .iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod answers {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(SAMPLE_GRID => Some(31); "with example data")]
+    #[test_case(personal_grid().as_slice() => Some(534); "with personal data")]
+    fn problem1(input: &[&str]) -> Option<u32> {
+        let (grid, start, end) = load_grid(input).unwrap();
+        shortest_path(&grid, start, end).map(|path| path.len())
+    }
+
+    #[test_case(SAMPLE_GRID => Some(29); "with example data")]
+    #[test_case(personal_grid().as_slice() => Some(525); "with personal data")]
+    fn problem2(input: &[&str]) -> Option<u32> {
+        let (grid, _, end) = load_grid(input).unwrap();
+        shortest_from_height(&grid, end, b'a').map(|path| path.len())
+    }
+
+    #[test_case(SAMPLE_GRID; "with example data")]
+    #[test_case(personal_grid().as_slice(); "with personal data")]
+    fn dijkstra_agrees_with_bfs(input: &[&str]) {
+        let (grid, start, end) = load_grid(input).unwrap();
+        let shortest = shortest_path(&grid, start, end).unwrap();
+        let (cheapest, cost) = cheapest_path(&grid, start, end, &PuzzleRules).unwrap();
 
-        shortest
+        assert_eq!(cheapest.len(), shortest.len());
+        assert_eq!(cost, shortest.len());
     }
 
     const SAMPLE_GRID: &[&str] = &[
@@ -1,46 +1,320 @@
 //! Day 13 - Distress signal
 
-use serde_json::{json, Value};
+use crate::line_error::LineError;
 use std::cmp::Ordering;
+use std::fmt::Display;
+use std::str::FromStr;
 
-pub fn parse_input(lines: &[&str]) -> Vec<Value> {
-    lines
-        .iter()
-        .filter_map(|&s| serde_json::from_str(s).ok())
-        .collect()
+/// A packet from the distress signal - either a number, or a list of more
+/// packets. Packets are ordered the way the puzzle describes, see
+/// [Packet::puzzle_cmp], with packets the puzzle can't tell apart ordered
+/// by their structure so that only identical packets are equal
+///
+/// # Examples
+/// ```
+/// use aoc2022::day13::*;
+///
+/// let lhs: Packet = "[[1],[2,3,4]]".parse().unwrap();
+/// let rhs: Packet = "[[1],4]".parse().unwrap();
+/// assert!(lhs < rhs);
+/// assert_eq!(lhs.to_string(), "[[1],[2,3,4]]");
+///
+/// let wrapped = Packet::List(vec![Packet::List(vec![Packet::Int(7)])]);
+/// assert_ne!(Packet::Int(7), wrapped);
+/// assert!(Packet::Int(7) < wrapped);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Packet {
+    Int(u64),
+    List(Vec<Packet>),
+}
+
+impl Packet {
+    /// Compare packets the way the puzzle does, where a number matches a
+    /// list holding just that number
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day13::*;
+    /// use std::cmp::Ordering;
+    ///
+    /// let wrapped = Packet::List(vec![Packet::List(vec![Packet::Int(7)])]);
+    /// assert_eq!(Packet::Int(7).puzzle_cmp(&wrapped), Ordering::Equal);
+    /// assert_eq!(Packet::Int(8).puzzle_cmp(&wrapped), Ordering::Greater);
+    /// ```
+    pub fn puzzle_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => Self::puzzle_cmp_lists(a, b),
+            (Self::Int(a), Self::List(b)) => Self::puzzle_cmp_lists(&[Self::Int(*a)], b),
+            (Self::List(a), Self::Int(b)) => Self::puzzle_cmp_lists(a, &[Self::Int(*b)]),
+        }
+    }
+
+    fn puzzle_cmp_lists(lhs: &[Packet], rhs: &[Packet]) -> Ordering {
+        lhs.iter()
+            .zip(rhs)
+            .map(|(a, b)| a.puzzle_cmp(b))
+            .find(|&ordering| ordering != Ordering::Equal)
+            .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+    }
+
+    /// Compare just the shape of the packets, with numbers before lists
+    fn structural_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.structural_cmp(b))
+                .find(|&ordering| ordering != Ordering::Equal)
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Self::Int(_), Self::List(_)) => Ordering::Less,
+            (Self::List(_), Self::Int(_)) => Ordering::Greater,
+        }
+    }
+}
+
+// Ties in the puzzle's ordering are broken by structure, so the ordering
+// agrees with the derived equality
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.puzzle_cmp(other).then_with(|| self.structural_cmp(other))
+    }
 }
 
-pub fn compare(lhs: &Value, rhs: &Value) -> Ordering {
-    match (lhs, rhs) {
-        (Value::Number(a), Value::Number(b)) => {
-            let a = a.as_u64().unwrap();
-            let b = b.as_u64().unwrap();
-            a.cmp(&b)
-        },
-
-        (Value::Array(a), Value::Array(b)) => {
-            for x in 0..std::cmp::min(a.len(), b.len()) {
-                let z = compare(&a[x], &b[x]);
-                if z != Ordering::Equal {
-                    return z;
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
                 }
+                write!(f, "]")
+            },
+        }
+    }
+}
+
+/// Things that can be wrong with a packet
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PacketErrorKind {
+    /// The packet stops before all its lists are closed
+    UnexpectedEnd,
+
+    /// A character that can't go here
+    UnexpectedChar(char),
+
+    /// A number too big to fit in 64 bits
+    NumberTooLarge,
+
+    /// More after the end of the packet
+    TrailingInput,
+
+    /// Lists nested more than [MAX_DEPTH] deep
+    TooDeep,
+}
+
+impl Display for PacketErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of packet"),
+            Self::UnexpectedChar(c) => write!(f, "unexpected {c:?}"),
+            Self::NumberTooLarge => write!(f, "number too large"),
+            Self::TrailingInput => write!(f, "unexpected input after the packet"),
+            Self::TooDeep => write!(f, "lists nested more than {MAX_DEPTH} deep"),
+        }
+    }
+}
+
+/// A packet that couldn't be parsed, and the (0-based) byte offset of the
+/// problem
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PacketError {
+    pub position: usize,
+    pub kind: PacketErrorKind,
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.position, self.kind)
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+/// How deeply lists can be nested in a packet. The parser (and comparing,
+/// printing and dropping packets) recurses once per level, so this keeps
+/// hostile input from overflowing the stack
+pub const MAX_DEPTH: usize = 256;
+
+/// Recursive descent parser, working straight off the bytes of the input.
+/// Only ever steps over ASCII, so the position is always a char boundary
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: PacketErrorKind) -> PacketError {
+        PacketError { position: self.position, kind }
+    }
+
+    fn unexpected(&self) -> PacketError {
+        match self.input[self.position..].chars().next() {
+            Some(c) => self.error(PacketErrorKind::UnexpectedChar(c)),
+            None => self.error(PacketErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), PacketError> {
+        if self.peek() != Some(expected) {
+            return Err(self.unexpected());
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn packet(&mut self) -> Result<Packet, PacketError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b'0'..=b'9') => self.int(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn int(&mut self) -> Result<Packet, PacketError> {
+        let start = self.position;
+        let mut value: u64 = 0;
+
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add((digit - b'0') as u64))
+                .ok_or(PacketError { position: start, kind: PacketErrorKind::NumberTooLarge })?;
+            self.position += 1;
+        }
+
+        Ok(Packet::Int(value))
+    }
+
+    fn list(&mut self) -> Result<Packet, PacketError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(PacketErrorKind::TooDeep));
+        }
+
+        self.expect(b'[')?;
+        self.skip_whitespace();
+        let mut items = vec![];
+
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Packet::List(items));
+        }
+
+        self.depth += 1;
+        loop {
+            items.push(self.packet()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    self.depth -= 1;
+                    return Ok(Packet::List(items));
+                },
+                _ => return Err(self.unexpected()),
             }
+        }
+    }
+}
 
-            a.len().cmp(&b.len())
-        },
+impl FromStr for Packet {
+    type Err = PacketError;
 
-        (Value::Number(a), Value::Array(_)) => {
-            compare(&json!(vec![a]), rhs)
-        },
+    /// # Examples
+    /// ```
+    /// use aoc2022::day13::*;
+    ///
+    /// assert_eq!("[1,[2]]".parse(), Ok(Packet::List(vec![Packet::Int(1), Packet::List(vec![Packet::Int(2)])])));
+    ///
+    /// let error = |position, kind| Err::<Packet, _>(PacketError { position, kind });
+    /// assert_eq!("[1,2".parse(), error(4, PacketErrorKind::UnexpectedEnd));
+    /// assert_eq!("[1,-2]".parse(), error(3, PacketErrorKind::UnexpectedChar('-')));
+    /// assert_eq!("{}".parse(), error(0, PacketErrorKind::UnexpectedChar('{')));
+    /// assert_eq!("[1]]".parse(), error(3, PacketErrorKind::TrailingInput));
+    /// assert_eq!("[99999999999999999999]".parse(), error(1, PacketErrorKind::NumberTooLarge));
+    /// assert_eq!("[1,é]".parse(), error(3, PacketErrorKind::UnexpectedChar('é')));
+    ///
+    /// // Whitespace between the parts is fine, but not inside numbers
+    /// assert_eq!(" [1, [ 2 ] ]\n".parse(), "[1,[2]]".parse::<Packet>());
+    /// assert_eq!("[1 2]".parse(), error(3, PacketErrorKind::UnexpectedChar('2')));
+    ///
+    /// let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+    /// assert_eq!(deep.parse(), error(MAX_DEPTH, PacketErrorKind::TooDeep));
+    /// assert!(deep[1..deep.len() - 1].parse::<Packet>().is_ok());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, position: 0, depth: 0 };
+        let packet = parser.packet()?;
+        parser.skip_whitespace();
 
-        (Value::Array(_), Value::Number(b)) => {
-            compare(lhs, &json!(vec![b]))
-        },
+        if parser.position < s.len() {
+            return Err(parser.error(PacketErrorKind::TrailingInput));
+        }
 
-        _ => Ordering::Equal,
+        Ok(packet)
     }
 }
 
+/// A packet in the input that couldn't be parsed
+pub type InputError = LineError<PacketError>;
+
+/// Parse every packet in the input, one per line. Blank lines are skipped
+///
+/// # Examples
+/// ```
+/// use aoc2022::day13::*;
+///
+/// let packets = parse_input(&["[1,1,3]", "[1,1,5]", "", "[]"]).unwrap();
+/// assert_eq!(packets.len(), 3);
+///
+/// let error = parse_input(&["[1]", "", "[\"x\"]"]).unwrap_err();
+/// assert_eq!(error.line, 3);
+/// ```
+pub fn parse_input(lines: &[&str]) -> Result<Vec<Packet>, InputError> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| line.trim().parse().map_err(|kind| InputError { line: idx + 1, kind }))
+        .collect()
+}
+
 #[cfg(test)]
 mod answers {
     use super::*;
@@ -50,9 +324,10 @@ mod answers {
     #[test_case(personal_input().as_slice() => 6187; "with personal data")]
     fn problem1(input: &[&str]) -> usize {
         parse_input(input)
+            .unwrap()
             .chunks_exact(2)
             .enumerate()
-            .filter(|(_, chunk)| matches!(compare(&chunk[0], &chunk[1]), Ordering::Less))
+            .filter(|(_, chunk)| chunk[0] < chunk[1])
             .map(|(idx, _)| idx + 1)
             .sum()
     }
@@ -60,23 +335,17 @@ mod answers {
     #[test_case(SAMPLE_INPUT => 140; "with example data")]
     #[test_case(personal_input().as_slice() => 23520; "with personal data")]
     fn problem2(input: &[&str]) -> usize {
-        let first_div = json!([[2]]);
-        let second_div = json!([[6]]);
-        let mut p: Vec<_> = parse_input(input);
-
-        p.push(first_div);
-        p.push(second_div);
-        p.sort_by(compare);
+        let dividers: Vec<Packet> = vec!["[[2]]".parse().unwrap(), "[[6]]".parse().unwrap()];
+        let mut p = parse_input(input).unwrap();
 
-        let mut decoder = 1;
+        p.extend(dividers.iter().cloned());
+        p.sort();
 
-        for (idx, _) in p.iter().enumerate() {
-            if p[idx] == json!([[2]]) || p[idx] == json!([[6]]) {
-                decoder *= idx + 1;
-            }
-        }
-            
-        decoder
+        p.iter()
+            .enumerate()
+            .filter(|(_, packet)| dividers.contains(packet))
+            .map(|(idx, _)| idx + 1)
+            .product()
     }
 
     const SAMPLE_INPUT: &[&str] = &[
@@ -105,6 +374,13 @@ mod answers {
         "[1,[2,[3,[4,[5,6,0]]]],8,9]",
     ];
 
+    #[test]
+    fn round_trip() {
+        for line in personal_input().into_iter().filter(|line| !line.is_empty()) {
+            assert_eq!(line.parse::<Packet>().unwrap().to_string(), line);
+        }
+    }
+
     fn personal_input() -> Vec<&'static str> {
         include_str!("./input/day13.txt")
             .lines()
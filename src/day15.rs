@@ -1,5 +1,6 @@
 //! Beacon exclusion zone
 
+use crate::interval::IntervalSet;
 use std::collections::HashMap;
 
 pub type Point = (i32, i32);
@@ -50,27 +51,14 @@ pub fn count_covered_cells(map: &SensorMap, target_row: i32) -> Vec<Range> {
 /// assert_eq!(reduce_ranges(vec![(1,2), (3, 4)]), vec![(1,4)]);
 /// assert_eq!(reduce_ranges(vec![(1,3), (1,2)]), vec![(1,3)]);
 /// ```
-pub fn reduce_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
-    ranges.sort();
-    let mut reduced: Vec<Range> = vec![];
-
-    'outer: for i in ranges {
-        for j in &mut reduced {
-            let overlaps_start = i.0 >= j.0 && i.0 <= j.1 + 1;
-            let overlaps_end = i.1 >= j.0 - 1 && i.1 <= j.1;
-
-            match (overlaps_start, overlaps_end) {
-                (true, true) => (),
-                (true, false) => j.1 = i.1,
-                (false, true) => j.0 = i.0,
-                (false, false) => continue,
-            }
-
-            continue 'outer;
-        }
-        reduced.push(i);
-    }
-    reduced
+pub fn reduce_ranges(ranges: Vec<Range>) -> Vec<Range> {
+    ranges
+        .into_iter()
+        .map(|(start, end)| start..=end)
+        .collect::<IntervalSet<_>>()
+        .iter()
+        .map(|range| range.into_inner())
+        .collect()
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 //! Day 3 - Rucksack organization
 
 use std::collections::HashSet;
+use std::fmt::Display;
 
 /// Given a string representing the entire contents of an elf's
 /// ruck sack, partition that into the contents of each separate
@@ -127,6 +128,178 @@ pub fn prioritize_badges(rucksacks: &[&str]) -> u32
         .sum()
 }
 
+/// Something wrong with a line (or a group of lines) of rucksack input.
+/// Line numbers are 1-based, to match what you'd see in an editor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The line has an odd number of items, so it can't be split evenly
+    /// into two compartments
+    OddLength { line: usize, len: usize },
+
+    /// The line contains something that isn't an item (i.e. not a letter).
+    /// Columns are 1-based and count characters, not bytes
+    InvalidItem { line: usize, column: usize, item: char },
+
+    /// The two compartments have nothing in common
+    NoSharedItem { line: usize },
+
+    /// The two compartments have more than one item type in common
+    MultipleSharedItems { line: usize, items: Vec<char> },
+
+    /// The group starting at `line` has no item common to every elf
+    NoBadge { line: usize },
+
+    /// The group starting at `line` has more than one candidate badge
+    MultipleBadges { line: usize, items: Vec<char> },
+
+    /// The group starting at `line` has fewer than three elves in it
+    IncompleteGroup { line: usize, size: usize },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let as_string = |items: &[char]| items.iter().collect::<String>();
+
+        match self {
+            Self::OddLength { line, len } =>
+                write!(f, "line {line}: odd number of items ({len})"),
+            Self::InvalidItem { line, column, item } =>
+                write!(f, "line {line}, column {column}: {item:?} is not an item"),
+            Self::NoSharedItem { line } =>
+                write!(f, "line {line}: compartments have no item in common"),
+            Self::MultipleSharedItems { line, items } =>
+                write!(f, "line {line}: compartments share several items ({})", as_string(items)),
+            Self::NoBadge { line } =>
+                write!(f, "group starting at line {line}: no common badge"),
+            Self::MultipleBadges { line, items } =>
+                write!(f, "group starting at line {line}: several candidate badges ({})", as_string(items)),
+            Self::IncompleteGroup { line, size } =>
+                write!(f, "group starting at line {line}: only {size} elves in the group"),
+        }
+    }
+}
+
+/// Like [priority_for], but returns `None` for anything that isn't an item
+/// instead of panicking
+///
+/// # Examples
+/// ```
+/// use aoc2022::day3::*;
+/// assert_eq!(try_priority_for('z'), Some(26));
+/// assert_eq!(try_priority_for('?'), None);
+/// ```
+pub fn try_priority_for(item: char) -> Option<u32> {
+    item.is_ascii_alphabetic().then(|| priority_for(item))
+}
+
+/// Sort a set of items so problems are reported in a predictable order
+fn sorted(items: HashSet<char>) -> Vec<char> {
+    let mut items = Vec::from_iter(items);
+    items.sort_unstable();
+    items
+}
+
+/// Check a single rucksack, appending anything wrong with it to `problems`
+fn validate_rucksack(line: usize, contents: &str, problems: &mut Vec<Problem>) {
+    let items = contents.chars().collect::<Vec<_>>();
+    let mut well_formed = true;
+
+    if items.len() % 2 != 0 {
+        problems.push(Problem::OddLength { line, len: items.len() });
+        well_formed = false;
+    }
+
+    for (idx, &item) in items.iter().enumerate() {
+        if try_priority_for(item).is_none() {
+            problems.push(Problem::InvalidItem { line, column: idx + 1, item });
+            well_formed = false;
+        }
+    }
+
+    // There's no meaningful answer for which items are in the wrong
+    // compartment if we can't even tell where the compartments are
+    if well_formed {
+        let (left, right) = items.split_at(items.len() / 2);
+        let left = HashSet::<_>::from_iter(left.iter().copied());
+        let right = HashSet::<_>::from_iter(right.iter().copied());
+        let shared = sorted(HashSet::from_iter(left.intersection(&right).copied()));
+
+        match shared.len() {
+            0 => problems.push(Problem::NoSharedItem { line }),
+            1 => (),
+            _ => problems.push(Problem::MultipleSharedItems { line, items: shared }),
+        }
+    }
+}
+
+/// Check a group of elves, appending anything wrong with their badges
+/// to `problems`
+fn validate_group(line: usize, group: &[&str], problems: &mut Vec<Problem>) {
+    if group.len() != 3 {
+        problems.push(Problem::IncompleteGroup { line, size: group.len() });
+        return;
+    }
+
+    // Anything that isn't an item has already been reported against its
+    // own line, so only consider actual items when looking for badges
+    let badges = group
+        .iter()
+        .map(|&contents| HashSet::<_>::from_iter(contents.chars().filter(char::is_ascii_alphabetic)))
+        .reduce(|accum, item| HashSet::from_iter(accum.intersection(&item).copied()))
+        .unwrap_or_default();
+
+    match badges.len() {
+        0 => problems.push(Problem::NoBadge { line }),
+        1 => (),
+        _ => problems.push(Problem::MultipleBadges { line, items: sorted(badges) }),
+    }
+}
+
+/// Given a set of rucksacks, check that each is well formed and that each
+/// group of three elves has exactly one badge. Problems are reported in
+/// line order, with each group's badge problems following its rucksacks
+///
+/// # Examples
+/// ```
+/// use aoc2022::day3::*;
+/// let rucksacks = [
+///     "vJrwpWtwJgWrhcsFMMfFFhFp",
+///     "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+///     "PmmdzqPrVvPwwTWBwg",
+/// ];
+/// assert_eq!(validate_rucksacks(&rucksacks), vec![]);
+///
+/// let rucksacks = [
+///     "vJrwpWtwJgWrhcsFMMfFFhFpx",
+///     "jqHRNqRjqzjGDL-LrsFMfFZSrLrFZsSL",
+///     "abcabc",
+///     "abcd",
+/// ];
+/// assert_eq!(validate_rucksacks(&rucksacks), vec![
+///     Problem::OddLength { line: 1, len: 25 },
+///     Problem::InvalidItem { line: 2, column: 15, item: '-' },
+///     Problem::MultipleSharedItems { line: 3, items: vec!['a', 'b', 'c'] },
+///     Problem::NoBadge { line: 1 },
+///     Problem::NoSharedItem { line: 4 },
+///     Problem::IncompleteGroup { line: 4, size: 1 },
+/// ]);
+/// ```
+pub fn validate_rucksacks(rucksacks: &[&str]) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    for (group_idx, group) in rucksacks.chunks(3).enumerate() {
+        let first_line = group_idx * 3 + 1;
+
+        for (offset, &contents) in group.iter().enumerate() {
+            validate_rucksack(first_line + offset, contents, &mut problems);
+        }
+
+        validate_group(first_line, group, &mut problems);
+    }
+
+    problems
+}
+
 #[cfg(test)]
 mod answers {
     use super::*;
@@ -148,4 +321,9 @@ mod answers {
     fn problem2() {
         assert_eq!(prioritize_badges(&INPUT), 2342);
     }
+
+    #[test]
+    fn validation() {
+        assert_eq!(validate_rucksacks(&INPUT), vec![]);
+    }
 }
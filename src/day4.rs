@@ -1,9 +1,12 @@
 //! Day 4 - Camp cleanup
 
-use std::cmp::*;
+use crate::interval::IntervalSet;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::ops::RangeInclusive;
 
-type Assignment = RangeInclusive<u8>;
+pub type Section = u32;
+pub type Assignment = RangeInclusive<Section>;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq,)]
 pub enum OverlapType {
@@ -27,16 +30,27 @@ pub enum OverlapType {
 /// assert_eq!(get_overlap_type((5..=7, 1..=10)), OverlapType::Full);
 /// assert_eq!(get_overlap_type((5..=7, 6..=8)), OverlapType::Partial);
 /// assert_eq!(get_overlap_type((3..=4, 5..=6)), OverlapType::None);
+///
+/// // Section IDs aren't limited to what fits in a byte
+/// assert_eq!(get_overlap_type((250..=300, 280..=1000)), OverlapType::Partial);
+///
+/// // Empty (back to front) assignments cover nothing, so can't overlap
+/// assert_eq!(get_overlap_type((5..=3, 1..=2)), OverlapType::None);
+/// assert_eq!(count_overlapping_assignments(&["5-3,1-2"], false), 0);
 /// ```
 pub fn get_overlap_type(assignments: (Assignment, Assignment)) -> OverlapType {
     let (lhs, rhs) = assignments;
+    if lhs.is_empty() || rhs.is_empty() {
+        return OverlapType::None;
+    }
 
-    let overlapping_range = *min(lhs.start(), rhs.start())..=*max(lhs.end(), rhs.end());
+    let lhs = IntervalSet::from(lhs);
+    let rhs = IntervalSet::from(rhs);
 
-    if overlapping_range.len() >= lhs.len() + rhs.len() {
-        OverlapType::None
-    } else if overlapping_range == lhs || overlapping_range == rhs {
+    if lhs.is_superset(&rhs) || rhs.is_superset(&lhs) {
         OverlapType::Full
+    } else if lhs.intersection(&rhs).is_empty() {
+        OverlapType::None
     } else {
         OverlapType::Partial
     }
@@ -87,6 +101,162 @@ pub fn count_overlapping_assignments(lines: &[&str], include_partial: bool) -> u
         })
 }
 
+/// The big picture across every elf in the camp, rather than just the
+/// pairs on each line
+#[derive(Clone, Debug, Default)]
+pub struct CampAnalysis {
+    /// Every elf's assignment. Elves are numbered in the order they appear,
+    /// so elf `n` comes from line `n / 2` (0-based) of the input
+    pub elves: Vec<Assignment>,
+
+    /// Runs of sections along with how many elves are assigned to each,
+    /// in ascending order. Sections nobody is assigned are left out
+    pub coverage: Vec<(Assignment, usize)>,
+
+    /// The most elves assigned to any one section
+    pub max_overlap: usize,
+
+    /// Every pair of elves `(a, b)` where elf `a`'s assignment fully
+    /// contains elf `b`'s. Elves with identical assignments only show up
+    /// once, with the lower numbered elf first
+    pub containments: Vec<(usize, usize)>,
+}
+
+impl CampAnalysis {
+    /// How many elves are assigned to a particular section
+    pub fn elves_covering(&self, section: Section) -> usize {
+        let idx = self.coverage.partition_point(|(run, _)| *run.end() < section);
+        self.coverage
+            .get(idx)
+            .filter(|(run, _)| run.contains(&section))
+            .map_or(0, |&(_, count)| count)
+    }
+
+    /// Every section assigned to at least one elf
+    pub fn covered(&self) -> IntervalSet<Section> {
+        self.coverage
+            .iter()
+            .map(|(run, _)| run.clone())
+            .collect()
+    }
+
+    /// Sections within the camp that nobody is assigned to
+    pub fn unassigned(&self, camp: Assignment) -> IntervalSet<Section> {
+        IntervalSet::from(camp).difference(&self.covered())
+    }
+
+    /// The sections that are covered by the most elves
+    pub fn busiest_sections(&self) -> IntervalSet<Section> {
+        self.coverage
+            .iter()
+            .filter(|&&(_, count)| count == self.max_overlap)
+            .map(|(run, _)| run.clone())
+            .collect()
+    }
+}
+
+/// Given a set of encoded assignment pairs, look at every elf's assignment
+/// at once to see how the whole camp is covered
+///
+/// # Examples
+/// ```
+/// use aoc2022::day4::*;
+/// let samples = [
+///     "2-4,6-8",
+///     "2-3,4-5",
+///     "5-7,7-9",
+///     "2-8,3-7",
+///     "6-6,4-6",
+///     "2-6,4-8",
+/// ];
+/// let camp = analyze_camp(&samples);
+///
+/// assert_eq!(camp.elves.len(), 12);
+/// assert_eq!(camp.coverage, vec![
+///     (2..=2, 4), (3..=3, 5), (4..=5, 7), (6..=6, 8), (7..=7, 6), (8..=8, 4), (9..=9, 1),
+/// ]);
+/// assert_eq!(camp.max_overlap, 8);
+/// assert_eq!(camp.elves_covering(5), 7);
+/// assert_eq!(camp.elves_covering(10), 0);
+/// assert_eq!(camp.unassigned(1..=10).iter().collect::<Vec<_>>(), vec![1..=1, 10..=10]);
+///
+/// // Elf 6 (2-8) contains elf 1 (6-8) from a completely different line
+/// assert!(camp.containments.contains(&(6, 1)));
+/// assert!(!camp.containments.contains(&(1, 6)));
+/// assert_eq!(camp.containments.len(), 29);
+///
+/// // Empty assignments keep their place, so elves still line up with lines
+/// let camp = analyze_camp(&["5-3,1-2", "1-10,4-4"]);
+/// assert_eq!(camp.elves, vec![5..=3, 1..=2, 1..=10, 4..=4]);
+/// assert_eq!(camp.containments, vec![(2, 1), (2, 3)]);
+/// ```
+pub fn analyze_camp(lines: &[&str]) -> CampAnalysis {
+    let elves = lines
+        .iter()
+        .flat_map(|&line| {
+            let (lhs, rhs) = parse_line(line);
+            [lhs, rhs]
+        })
+        .collect::<Vec<_>>();
+
+    // Sweep along the sections, keeping track of how many elves start
+    // and stop being assigned at each point. Elves with empty (or back to
+    // front) assignments still keep their numbers, but cover nothing
+    let mut deltas = BTreeMap::<Section, isize>::new();
+    for elf in elves.iter().filter(|elf| !elf.is_empty()) {
+        *deltas.entry(*elf.start()).or_default() += 1;
+        if let Some(after) = elf.end().checked_add(1) {
+            *deltas.entry(after).or_default() -= 1;
+        }
+    }
+
+    let mut coverage: Vec<(Assignment, usize)> = vec![];
+    let mut active = 0;
+    let mut boundaries = deltas.into_iter().peekable();
+    while let Some((section, delta)) = boundaries.next() {
+        active += delta;
+        if active > 0 {
+            let end = boundaries.peek().map_or(Section::MAX, |&(next, _)| next - 1);
+
+            // One elf finishing just as another starts doesn't change anything
+            match coverage.last_mut() {
+                Some((run, count)) if *count == active as usize && *run.end() + 1 == section => {
+                    *run = *run.start()..=end;
+                },
+                _ => coverage.push((section..=end, active as usize)),
+            }
+        }
+    }
+
+    let max_overlap = coverage
+        .iter()
+        .map(|&(_, count)| count)
+        .max()
+        .unwrap_or(0);
+
+    // Sort by start, widest first, so anything an elf contains must come
+    // after it and before the first elf that starts past its end
+    let mut by_start = (0..elves.len())
+        .filter(|&idx| !elves[idx].is_empty())
+        .collect::<Vec<_>>();
+    by_start.sort_by_key(|&idx| (*elves[idx].start(), Reverse(*elves[idx].end())));
+
+    let mut containments = vec![];
+    for (pos, &outer) in by_start.iter().enumerate() {
+        for &inner in by_start[pos + 1..]
+            .iter()
+            .take_while(|&&inner| elves[inner].start() <= elves[outer].end())
+        {
+            if elves[inner].end() <= elves[outer].end() {
+                containments.push((outer, inner));
+            }
+        }
+    }
+    containments.sort_unstable();
+
+    CampAnalysis { elves, coverage, max_overlap, containments }
+}
+
 #[cfg(test)]
 mod answers {
     use super::*;
@@ -108,4 +278,18 @@ mod answers {
     fn problem2() {
         assert_eq!(count_overlapping_assignments(&INPUT, true), 839);
     }
+
+    #[test]
+    fn whole_camp() {
+        let camp = analyze_camp(&INPUT);
+
+        // Every pair that overlaps fully on a line is one of the camp-wide
+        // containments too
+        let same_line = camp.containments
+            .iter()
+            .filter(|(a, b)| a / 2 == b / 2)
+            .map(|(a, _)| a / 2)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(same_line.len(), 560);
+    }
 }
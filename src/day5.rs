@@ -1,11 +1,114 @@
 //! Day 5 - Supply Stacks
 
 use std::collections::VecDeque;
+use std::fmt::Display;
 use std::str::FromStr;
 
-#[derive(Default)]
+/// A crate's label, which is usually (but not always) a single letter
+pub type Crate = String;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Inventory {
-    pub stacks: Vec<VecDeque<char>>,
+    pub stacks: Vec<VecDeque<Crate>>,
+}
+
+/// Reasons the puzzle input can't be understood
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A line of the drawing has something other than crates in it
+    MalformedCrates(String),
+
+    /// A line isn't of the form `move N from A to B`
+    MalformedDirection(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MalformedCrates(line) => write!(f, "can't make sense of crates in {line:?}"),
+            Self::MalformedDirection(line) => write!(f, "can't make sense of direction {line:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Find every `[label]` in a line of the drawing, along with the (char)
+/// position of its center
+fn parse_crates(line: &str) -> Result<Vec<(usize, Crate)>, ParseError> {
+    let malformed = || ParseError::MalformedCrates(line.to_string());
+    let mut crates = vec![];
+    let mut chars = line.chars().enumerate();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '[' => {
+                let label = chars
+                    .by_ref()
+                    .map_while(|(_, c)| (c != ']').then_some(c))
+                    .collect::<String>();
+
+                // map_while eats the closing bracket, so make sure there was one
+                let end = start + label.chars().count() + 1;
+                if label.is_empty() || line.chars().nth(end) != Some(']') {
+                    return Err(malformed());
+                }
+
+                crates.push(((start + end) / 2, label));
+            },
+            c if c.is_whitespace() => (),
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(crates)
+}
+
+/// If this line is the numbered footer of the drawing, find the (char)
+/// position of the center of each stack number
+fn parse_footer(line: &str) -> Option<Vec<(usize, usize)>> {
+    let mut columns = vec![];
+    let mut chars = line.chars().enumerate().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_ascii_digit() {
+            let mut number = String::from(c);
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                number.push(c);
+            }
+
+            let center = start + (number.len() - 1) / 2;
+            columns.push((center, number.parse().ok()?));
+        } else if !c.is_whitespace() {
+            return None;
+        }
+    }
+
+    (!columns.is_empty()).then_some(columns)
+}
+
+/// Where the stack numbers would be in a drawing `width` characters wide,
+/// if it uses the puzzle's fixed four character wide columns
+fn fixed_columns(width: usize) -> Vec<(usize, usize)> {
+    (0..width.div_ceil(4))
+        .map(|idx| (idx * 4 + 1, idx + 1))
+        .collect()
+}
+
+/// Which stack a crate centred at `position` belongs to. Each column
+/// reaches halfway to the stack numbers either side of it, or as far as it
+/// does on its other side at the edges. A lone column is four characters
+/// wide, like the puzzle's
+fn column_of(position: usize, columns: &[(usize, usize)]) -> Option<usize> {
+    let idx = (0..columns.len()).min_by_key(|&idx| columns[idx].0.abs_diff(position))?;
+    let (center, stack) = columns[idx];
+
+    let before = idx.checked_sub(1).map(|prev| center - columns[prev].0);
+    let after = columns.get(idx + 1).map(|&(next, _)| next - center);
+    let width = if position < center { before.or(after) } else { after.or(before) };
+    let width = width.unwrap_or(4);
+
+    (2 * center.abs_diff(position) < width).then_some(stack)
 }
 
 impl Inventory {
@@ -13,59 +116,368 @@ impl Inventory {
         self.stacks = vec![VecDeque::new(); count];
     }
 
-    pub fn add_inventory(&mut self, line: &str) {
-        self.stacks.resize(line.len() / 4 + 1, VecDeque::new());
+    /// Add a line of crates, putting each one on the stack whose column
+    /// it's in. `columns` is the position of each stack number and the
+    /// number itself, left to right, as found by [parse_footer]. Crates
+    /// outside every column, or sharing one with another crate, are an error
+    fn add_labelled_inventory(&mut self, line: &str, columns: &[(usize, usize)]) -> Result<(), ParseError> {
+        let malformed = || ParseError::MalformedCrates(line.to_string());
+        let mut filled = vec![false; self.stacks.len()];
 
-        for (idx, krate) in line
-            .chars()
-            .collect::<Vec<_>>()
-            .chunks(4)
-            .enumerate()
-        {
-            if krate[0] == '[' {
-                self.stacks[idx].push_front(krate[1]);
+        for (position, krate) in parse_crates(line)? {
+            let stack = column_of(position, columns).ok_or_else(malformed)?;
+            if std::mem::replace(&mut filled[stack - 1], true) {
+                return Err(malformed());
             }
+
+            self.stacks[stack - 1].push_front(krate);
+        }
+
+        Ok(())
+    }
+
+    /// Check that a direction can actually be carried out, i.e. that both
+    /// stacks exist and there are enough crates to move
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day5::*;
+    ///
+    /// let mut inventory: Inventory = "[A] [B]".parse().unwrap();
+    /// let mut direction: Direction = "move 1 from 0 to 2".parse().unwrap();
+    /// assert_eq!(inventory.execute_lifo(direction), Err(CraneError::NoSuchStack(0)));
+    ///
+    /// direction = "move 2 from 1 to 2".parse().unwrap();
+    /// assert_eq!(
+    ///     inventory.execute_fifo(direction),
+    ///     Err(CraneError::NotEnoughCrates { stack: 1, wanted: 2, available: 1 }),
+    /// );
+    /// assert_eq!(inventory.stacks, vec![vec!["A"], vec!["B"]]);
+    /// ```
+    pub fn validate(&self, direction: &Direction) -> Result<(), CraneError> {
+        let index_of = |stack: usize| stack
+            .checked_sub(1)
+            .filter(|&idx| idx < self.stacks.len())
+            .ok_or(CraneError::NoSuchStack(stack));
+
+        let from = index_of(direction.from_idx)?;
+        index_of(direction.to_idx)?;
+
+        let wanted = direction.count as usize;
+        let available = self.stacks[from].len();
+        if available < wanted {
+            return Err(CraneError::NotEnoughCrates { stack: direction.from_idx, wanted, available });
         }
+
+        Ok(())
     }
 
-    pub fn execute_lifo(&mut self, direction: Direction) {
+    pub fn execute_lifo(&mut self, direction: Direction) -> Result<(), CraneError> {
+        self.validate(&direction)?;
+
         for _ in 0..direction.count {
             if let Some(krate) = self.stacks[direction.from_idx - 1].pop_back() {
                 self.stacks[direction.to_idx - 1].push_back(krate);
             }
         }
+
+        Ok(())
     }
 
-    pub fn execute_fifo(&mut self, direction: Direction) {
+    pub fn execute_fifo(&mut self, direction: Direction) -> Result<(), CraneError> {
+        self.validate(&direction)?;
+
         // Split last N off of the from stack and append them to the to stack
         let from = &mut self.stacks[direction.from_idx - 1];
         let mut to_move = from.split_off(from.len() - direction.count as usize);
 
         let to = &mut self.stacks[direction.to_idx - 1];
         to.append(&mut to_move);
+
+        Ok(())
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &VecDeque<char>> {
+    pub fn iter(&self) -> impl Iterator<Item = &VecDeque<Crate>> {
         self.stacks.iter()
     }
 }
 
+impl FromStr for Inventory {
+    type Err = ParseError;
+
+    /// Parse just the crate drawing. If there's a numbered footer, that's
+    /// used to figure out which stack each crate belongs to, which allows
+    /// for more than nine stacks and labels longer than a single character.
+    /// Without one, the puzzle's fixed width columns are assumed. Either
+    /// way, crates have to sit within a column, one per column on each line
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day5::*;
+    ///
+    /// let drawing = "[A]                                     [K]\n[AB][B] [C] [D] [E] [F] [G] [H] [I] [J] [L]";
+    /// let inventory: Inventory = drawing.parse().unwrap();
+    /// assert_eq!(inventory.stacks.len(), 11);
+    /// assert_eq!(inventory.stacks[0], vec!["AB", "A"]);
+    /// assert_eq!(inventory.stacks[10], vec!["L", "K"]);
+    ///
+    /// assert_eq!(
+    ///     "[A] [B]\n 0   1 ".parse::<Inventory>(),
+    ///     Err(ParseError::MalformedCrates(" 0   1 ".into())),
+    /// );
+    ///
+    /// // No stack number for the second crate
+    /// assert_eq!(
+    ///     "[A]    [B]\n 1".parse::<Inventory>(),
+    ///     Err(ParseError::MalformedCrates("[A]    [B]".into())),
+    /// );
+    ///
+    /// // Both crates are nearest stack 1
+    /// assert_eq!(
+    ///     "[A][B]     \n 1       2".parse::<Inventory>(),
+    ///     Err(ParseError::MalformedCrates("[A][B]     ".into())),
+    /// );
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut inventory = Self::default();
+        let lines = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        let columns = match lines.iter().find_map(|&line| Some((line, parse_footer(line)?))) {
+            Some((footer, columns)) => {
+                if columns.iter().any(|&(_, stack)| stack == 0) {
+                    return Err(ParseError::MalformedCrates(footer.to_string()));
+                }
+                columns
+            },
+            None => fixed_columns(lines.iter().map(|line| line.chars().count()).max().unwrap_or(0)),
+        };
+
+        let num_stacks = columns
+            .iter()
+            .map(|&(_, stack)| stack)
+            .max()
+            .unwrap_or(0);
+        inventory.reserve(num_stacks);
+
+        for line in lines
+            .into_iter()
+            .take_while(|line| parse_footer(line).is_none())
+        {
+            inventory.add_labelled_inventory(line, &columns)?;
+        }
+
+        Ok(inventory)
+    }
+}
+
+/// Draws the inventory the same way the puzzle input does, crates in
+/// `[X]` columns with the stack numbers along the bottom. Columns get wider
+/// as needed to fit longer labels or stack numbers
+///
+/// # Examples
+/// ```
+/// use aoc2022::day5::*;
+///
+/// let drawing = concat!(
+///     "    [D]    \n",
+///     "[N] [C]    \n",
+///     "[Z] [M] [P]\n",
+///     " 1   2   3 \n",
+/// );
+///
+/// let inventory: Inventory = drawing.parse().unwrap();
+/// assert_eq!(inventory.to_string(), drawing);
+/// assert_eq!(inventory.to_string().parse(), Ok(inventory));
+///
+/// let drawing = concat!(
+///     "[AB]                                                  \n",
+///     "[C]                                              [XYZ]\n",
+///     " 1    2    3    4    5    6    7    8    9    10   11 \n",
+/// );
+///
+/// let inventory: Inventory = drawing.parse().unwrap();
+/// assert_eq!(inventory.stacks[0], vec!["C", "AB"]);
+/// assert_eq!(inventory.stacks[10], vec!["XYZ"]);
+/// assert_eq!(inventory.to_string().parse(), Ok(inventory));
+/// ```
+impl Display for Inventory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks
+            .iter()
+            .map(VecDeque::len)
+            .max()
+            .unwrap_or(0);
+
+        let longest_label = self.stacks
+            .iter()
+            .flatten()
+            .map(|krate| krate.chars().count())
+            .max()
+            .unwrap_or(1);
+        let width = (longest_label + 2).max(self.stacks.len().to_string().len());
+
+        for level in (0..height).rev() {
+            let row = self.stacks
+                .iter()
+                .map(|stack| {
+                    let krate = stack.get(level).map_or(String::new(), |krate| format!("[{krate}]"));
+                    format!("{krate:<width$}")
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+
+        let footer = (1..=self.stacks.len())
+            .map(|idx| format!("{idx:^width$}"))
+            .collect::<Vec<_>>();
+        writeln!(f, "{}", footer.join(" "))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Direction {
     pub count: u8,
     pub from_idx: usize,
     pub to_idx: usize,
 }
 
+impl Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from_idx, self.to_idx)
+    }
+}
+
+/// Reasons a crane can't carry out a direction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CraneError {
+    /// The (1-based) stack doesn't exist
+    NoSuchStack(usize),
+
+    /// The stack doesn't have as many crates as the direction wants to move
+    NotEnoughCrates { stack: usize, wanted: usize, available: usize },
+}
+
+impl Display for CraneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoSuchStack(stack) => write!(f, "there is no stack {stack}"),
+            Self::NotEnoughCrates { stack, wanted, available } =>
+                write!(f, "can't move {wanted} crates from stack {stack}, it only has {available}"),
+        }
+    }
+}
+
+impl std::error::Error for CraneError {}
+
+/// A model of crane. Every crane takes the top N crates off one stack and
+/// puts them on top of another, but they differ in what order the crates end
+/// up in and how many lifts it takes to get them there
+pub trait Crane {
+    /// Carry out a single direction, returning how many lifts it took. A
+    /// direction that can't be carried out must leave the inventory untouched
+    fn execute(&self, inventory: &mut Inventory, direction: Direction) -> Result<usize, CraneError>;
+}
+
+/// Moves one crate at a time, so the crates end up in reverse order
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn execute(&self, inventory: &mut Inventory, direction: Direction) -> Result<usize, CraneError> {
+        inventory.execute_lifo(direction)?;
+        Ok(direction.count as usize)
+    }
+}
+
+/// Moves all the crates in one go, so they keep their order
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn execute(&self, inventory: &mut Inventory, direction: Direction) -> Result<usize, CraneError> {
+        inventory.execute_fifo(direction)?;
+        Ok(usize::from(direction.count > 0))
+    }
+}
+
+/// Like the CrateMover 9001, but can only lift so many crates at once. Bigger
+/// moves get split into batches, each of which keeps its order. A capacity
+/// of zero is treated as a capacity of one
+///
+/// # Examples
+/// ```
+/// use aoc2022::day5::*;
+/// use std::collections::VecDeque;
+///
+/// let stack = ["A", "B", "C", "D", "E"].map(String::from).into();
+/// let mut inventory = Inventory { stacks: vec![stack, VecDeque::new()] };
+/// let crane = LimitedCapacityCrane(2);
+///
+/// assert_eq!(crane.execute(&mut inventory, "move 5 from 1 to 2".parse().unwrap()), Ok(3));
+/// assert_eq!(inventory.stacks[1], vec!["D", "E", "B", "C", "A"]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct LimitedCapacityCrane(pub usize);
+
+impl Crane for LimitedCapacityCrane {
+    fn execute(&self, inventory: &mut Inventory, direction: Direction) -> Result<usize, CraneError> {
+        inventory.validate(&direction)?;
+
+        let capacity = self.0.max(1);
+        let mut remaining = direction.count as usize;
+        let mut lifts = 0;
+
+        while remaining > 0 {
+            let batch = remaining.min(capacity);
+            inventory.execute_fifo(Direction { count: batch as u8, ..direction })?;
+            remaining -= batch;
+            lifts += 1;
+        }
+
+        Ok(lifts)
+    }
+}
+
+/// Moves one crate at a time, but starts with the lowest of the crates being
+/// moved. The crates keep their order like the CrateMover 9001, but it takes
+/// as many lifts as the CrateMover 9000
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BottomFirstCrane;
+
+impl Crane for BottomFirstCrane {
+    fn execute(&self, inventory: &mut Inventory, direction: Direction) -> Result<usize, CraneError> {
+        inventory.validate(&direction)?;
+
+        let count = direction.count as usize;
+        let bottom = inventory.stacks[direction.from_idx - 1].len() - count;
+
+        for _ in 0..count {
+            let krate = inventory.stacks[direction.from_idx - 1].remove(bottom).unwrap();
+            inventory.stacks[direction.to_idx - 1].push_back(krate);
+        }
+
+        Ok(count)
+    }
+}
+
 impl FromStr for Direction {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let pieces = s.split_whitespace().collect::<Vec<_>>();
-        Ok(Self {
-            count: pieces[1].parse().unwrap_or(0),
-            from_idx: pieces[3].parse().unwrap_or(0),
-            to_idx: pieces[5].parse().unwrap_or(0),
-        })
+        let malformed = || ParseError::MalformedDirection(s.to_string());
+        let tokens = s.split_whitespace().collect::<Vec<_>>();
+
+        match tokens[..] {
+            ["move", count, "from", from_idx, "to", to_idx] => Ok(Self {
+                count: count.parse().map_err(|_| malformed())?,
+                from_idx: from_idx.parse().map_err(|_| malformed())?,
+                to_idx: to_idx.parse().map_err(|_| malformed())?,
+            }),
+            _ => Err(malformed()),
+        }
     }
 }
 
@@ -89,27 +501,43 @@ impl FromStr for Direction {
 /// "#;
 /// 
 /// let (inventory, directions) = parse_input(sample);
-/// assert_eq!(inventory.stacks, vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]);
+/// assert_eq!(inventory.stacks, vec![vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]);
 /// ```
 pub fn parse_input(input: &str) -> (Inventory, Vec<Direction>) {
-    let lines = input.lines();
-    let mut stacks = Inventory::default();
-    let mut directions = vec![];
-
-    for line in lines {
-        match line {
-            crates if crates.trim_start().starts_with('[') => {
-                stacks.add_inventory(crates);
-            },
-            direction if direction.starts_with('m') => {
-                directions.push(direction.parse().unwrap());
-            },
-            _ => (),
-        }
-    }
+    try_parse_input(input).expect("Ill-formed input")
+}
 
-    (stacks, directions)
-} 
+/// Like [parse_input], but reports problems with the input rather than
+/// panicking
+///
+/// # Examples
+/// ```
+/// use aoc2022::day5::*;
+///
+/// let sample = r#"
+/// [A]
+///  1
+///
+/// move 1 from 1 to one
+/// "#;
+///
+/// assert_eq!(try_parse_input(sample), Err(ParseError::MalformedDirection("move 1 from 1 to one".into())));
+/// ```
+pub fn try_parse_input(input: &str) -> Result<(Inventory, Vec<Direction>), ParseError> {
+    // The drawing is everything up to the first direction
+    let (drawing, directions): (Vec<_>, Vec<_>) = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .partition(|line| !line.trim_start().starts_with("move"));
+
+    let stacks = drawing.join("\n").parse()?;
+    let directions = directions
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+
+    Ok((stacks, directions))
+}
 
 /// Given a set of crate stacks and a set of crane directions,
 /// run the simulation to rearrange the crates
@@ -130,16 +558,12 @@ pub fn parse_input(input: &str) -> (Inventory, Vec<Direction>) {
 /// "#;
 /// 
 /// let (inventory, directions,) = parse_input(input);
-/// let inventory = execute_instructions_9000(inventory, directions);
+/// let inventory = execute_instructions_9000(inventory, directions).unwrap();
 /// 
-/// assert_eq!(inventory.stacks, vec![vec!['C'], vec!['M'], vec!['P', 'D', 'N', 'Z']])
+/// assert_eq!(inventory.stacks, vec![vec!["C"], vec!["M"], vec!["P", "D", "N", "Z"]])
 /// ```
-pub fn execute_instructions_9000(mut stacks: Inventory, directions: Vec<Direction>) -> Inventory {
-    for direction in directions {
-        stacks.execute_lifo(direction);
-    }
-
-    stacks
+pub fn execute_instructions_9000(stacks: Inventory, directions: Vec<Direction>) -> Result<Inventory, CraneError> {
+    simulate(CrateMover9000, stacks, directions).map(|report| report.inventory)
 }
 
 /// Given a set of crate stacks and a set of crane directions,
@@ -161,16 +585,213 @@ pub fn execute_instructions_9000(mut stacks: Inventory, directions: Vec<Directio
 /// "#;
 /// 
 /// let (inventory, directions,) = parse_input(input);
-/// let inventory = execute_instructions_9001(inventory, directions);
+/// let inventory = execute_instructions_9001(inventory, directions).unwrap();
 /// 
-/// assert_eq!(inventory.stacks, vec![vec!['M'], vec!['C'], vec!['P', 'Z', 'N', 'D']])
+/// assert_eq!(inventory.stacks, vec![vec!["M"], vec!["C"], vec!["P", "Z", "N", "D"]])
+/// ```
+pub fn execute_instructions_9001(stacks: Inventory, directions: Vec<Direction>) -> Result<Inventory, CraneError> {
+    simulate(CrateMover9001, stacks, directions).map(|report| report.inventory)
+}
+
+/// Steps through a crane program one direction at a time. Unless it's
+/// created [without_undo](Simulation::without_undo), it keeps enough
+/// history to undo each step again
+///
+/// # Examples
+/// ```
+/// use aoc2022::day5::*;
+/// let input = r#"
+///     [D]    
+/// [N] [C]    
+/// [Z] [M] [P]
+///  1   2   3 
+///
+/// move 1 from 2 to 1
+/// move 3 from 1 to 3
+/// move 2 from 2 to 1
+/// move 1 from 1 to 2 
+/// move 5 from 3 to 4
+/// "#;
+///
+/// let (inventory, directions) = parse_input(input);
+/// let mut simulation = Simulation::new(inventory, directions, CrateMover9000);
+///
+/// // The last direction is nonsense, so the program stops just before it
+/// assert_eq!(simulation.run(), Err(CraneError::NoSuchStack(4)));
+/// assert_eq!(simulation.position(), 4);
+/// assert_eq!(simulation.lifts(), 7);
+/// assert_eq!(simulation.inventory().stacks, vec![vec!["C"], vec!["M"], vec!["P", "D", "N", "Z"]]);
+///
+/// // Go back and have a look at what happened partway through
+/// simulation.replay_to(1).unwrap();
+/// assert_eq!(simulation.inventory().stacks, vec![vec!["Z", "N", "D"], vec!["M", "C"], vec!["P"]]);
+///
+/// assert!(simulation.undo());
+/// assert!(!simulation.undo());
+/// assert_eq!(simulation.inventory().stacks, vec![vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]);
+/// assert_eq!(simulation.lifts(), 0);
+///
+/// // Nothing to go back to without the history
+/// let (inventory, directions) = parse_input(input);
+/// let mut simulation = Simulation::without_undo(inventory, directions, CrateMover9000);
+/// simulation.replay_to(2).unwrap();
+/// assert!(!simulation.undo());
+/// assert_eq!(simulation.position(), 2);
 /// ```
-pub fn execute_instructions_9001(mut stacks: Inventory, directions: Vec<Direction>) -> Inventory {
-    for direction in directions {
-        stacks.execute_fifo(direction);
+pub struct Simulation<C> {
+    inventory: Inventory,
+    program: Vec<Direction>,
+    position: usize,
+    lifts: usize,
+
+    /// The crates taken and lifts needed by each step so far, if we're
+    /// keeping track
+    history: Option<Vec<(Vec<Crate>, usize)>>,
+    crane: C,
+}
+
+impl<C: Crane> Simulation<C> {
+    pub fn new(inventory: Inventory, program: Vec<Direction>, crane: C) -> Self {
+        Self { inventory, program, position: 0, lifts: 0, history: Some(vec![]), crane }
+    }
+
+    /// A simulation that only ever runs forwards, so doesn't need to keep
+    /// copies of the crates it moves
+    pub fn without_undo(inventory: Inventory, program: Vec<Direction>, crane: C) -> Self {
+        Self { history: None, ..Self::new(inventory, program, crane) }
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn program(&self) -> &[Direction] {
+        &self.program
+    }
+
+    /// How many directions have been executed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// How many lifts the crane has needed so far
+    pub fn lifts(&self) -> usize {
+        self.lifts
+    }
+
+    /// Execute the next direction, if there is one. A direction that fails
+    /// leaves everything as it was, so it can be inspected or undone
+    pub fn step(&mut self) -> Option<Result<(), CraneError>> {
+        let direction = *self.program.get(self.position())?;
+
+        if let Err(err) = self.inventory.validate(&direction) {
+            return Some(Err(err));
+        }
+
+        // Whatever order the crane puts them down in, it always takes the
+        // top N crates off one stack and puts them on top of another. So
+        // remembering what they looked like beforehand is enough to undo it
+        let taken = self.history.is_some().then(|| {
+            let from = &self.inventory.stacks[direction.from_idx - 1];
+            from.range(from.len() - direction.count as usize..)
+                .cloned()
+                .collect()
+        });
+
+        Some(self.crane
+            .execute(&mut self.inventory, direction)
+            .map(|lifts| {
+                self.position += 1;
+                self.lifts += lifts;
+                if let (Some(history), Some(taken)) = (self.history.as_mut(), taken) {
+                    history.push((taken, lifts));
+                }
+            }))
+    }
+
+    /// Undo the most recently executed direction. Returns false if we're
+    /// already back at the start, or not keeping the history to undo with
+    pub fn undo(&mut self) -> bool {
+        let Some((taken, lifts)) = self.history.as_mut().and_then(Vec::pop) else {
+            return false;
+        };
+
+        self.position -= 1;
+        self.lifts -= lifts;
+
+        let direction = self.program[self.position];
+        let to = &mut self.inventory.stacks[direction.to_idx - 1];
+        to.truncate(to.len() - taken.len());
+        self.inventory.stacks[direction.from_idx - 1].extend(taken);
+
+        true
+    }
+
+    /// Run forwards or backwards until exactly `step` directions have been
+    /// executed (or the whole program, if it's shorter than that). Without
+    /// undo, it can only go forwards
+    pub fn replay_to(&mut self, step: usize) -> Result<(), CraneError> {
+        let step = step.min(self.program.len());
+
+        while self.position() > step && self.undo() {}
+
+        while self.position() < step {
+            self.step().unwrap_or(Ok(()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the rest of the program
+    pub fn run(&mut self) -> Result<(), CraneError> {
+        self.replay_to(self.program.len())
     }
+}
+
+/// The outcome of running a whole program with a particular crane
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraneReport {
+    pub inventory: Inventory,
+    pub lifts: usize,
+}
+
+/// Run a whole crane program, keeping track of how many lifts it took
+///
+/// # Examples
+/// ```
+/// use aoc2022::day5::*;
+/// let input = r#"
+///     [D]    
+/// [N] [C]    
+/// [Z] [M] [P]
+///  1   2   3 
+///
+/// move 1 from 2 to 1
+/// move 3 from 1 to 3
+/// move 2 from 2 to 1
+/// move 1 from 1 to 2 
+/// "#;
+///
+/// let (inventory, directions) = parse_input(input);
+///
+/// assert_eq!(simulate(CrateMover9000, inventory.clone(), directions.clone()).unwrap().lifts, 7);
+/// assert_eq!(simulate(CrateMover9001, inventory.clone(), directions.clone()).unwrap().lifts, 4);
+///
+/// let report = simulate(LimitedCapacityCrane(2), inventory.clone(), directions.clone()).unwrap();
+/// assert_eq!(report.lifts, 5);
+///
+/// let report = simulate(BottomFirstCrane, inventory.clone(), directions.clone()).unwrap();
+/// assert_eq!(report.lifts, 7);
+/// assert_eq!(report.inventory.stacks, vec![vec!["M"], vec!["C"], vec!["P", "Z", "N", "D"]]);
+/// ```
+pub fn simulate<C: Crane>(crane: C, inventory: Inventory, directions: Vec<Direction>) -> Result<CraneReport, CraneError> {
+    let mut simulation = Simulation::without_undo(inventory, directions, crane);
+    simulation.run()?;
 
-    stacks
+    Ok(CraneReport {
+        lifts: simulation.lifts(),
+        inventory: simulation.inventory,
+    })
 }
 
 #[cfg(test)]
@@ -183,8 +804,9 @@ mod answers {
     pub fn problem1() {
         let (stacks, directions) = parse_input(INPUT);
         let result = execute_instructions_9000(stacks, directions)
+            .unwrap()
             .iter()
-            .map(|v| v.back().unwrap())
+            .map(|v| v.back().unwrap().as_str())
             .collect::<String>()
             .clone();
 
@@ -195,11 +817,25 @@ mod answers {
     pub fn problem2() {
         let (stacks, directions) = parse_input(INPUT);
         let result = execute_instructions_9001(stacks, directions)
+            .unwrap()
             .iter()
-            .map(|v| v.back().unwrap())
+            .map(|v| v.back().unwrap().as_str())
             .collect::<String>()
             .clone();
 
         assert_eq!(&result, "VRZGHDFBQ");
     }
+
+    #[test]
+    pub fn round_trip() {
+        let (stacks, _) = parse_input(INPUT);
+        let drawing = INPUT
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .flat_map(|line| [line, "\n"])
+            .collect::<String>();
+
+        assert_eq!(stacks.to_string(), drawing);
+        assert_eq!(drawing.parse(), Ok(stacks));
+    }
 }
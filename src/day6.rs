@@ -1,15 +1,361 @@
 //! Day 6 - Tuning trouble
 
-use core::hash::Hash;
-use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io::{BufReader, Read};
 
-trait FunOverEngineering {
-    fn count_distinct(&self) -> usize;
+/// Watches a stream of bytes go by, one at a time, and spots whenever the
+/// last `len` of them were all different. Keeps a count of each byte value
+/// in the window so that each new byte is handled in constant time
+///
+/// # Examples
+/// ```
+/// use aoc2022::day6::*;
+///
+/// let mut detector = MarkerDetector::new(3);
+/// let found = b"aabcbd"
+///     .iter()
+///     .map(|&byte| detector.push(byte))
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(found, vec![None, None, None, Some(4), None, Some(6)]);
+/// ```
+///
+/// ```should_panic
+/// aoc2022::day6::MarkerDetector::new(0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MarkerDetector {
+    len: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    duplicates: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    /// # Panics
+    /// If `len` is zero, since every position would be a marker
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0, "markers must be at least one byte long");
+
+        Self {
+            len,
+            window: VecDeque::with_capacity(len + 1),
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
+        }
+    }
+
+    /// How many bytes have been seen so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Feed the next byte in. If that completes a marker, returns how many
+    /// bytes have been seen so far, i.e. the offset just past the marker
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+        self.window.push_back(byte);
+
+        // `duplicates` is how many bytes in the window have been seen earlier
+        // in the window too, so it's zero exactly when they're all distinct
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] > 1 {
+            self.duplicates += 1;
+        }
+
+        if self.window.len() > self.len {
+            let old = self.window.pop_front().unwrap();
+            self.counts[old as usize] -= 1;
+            if self.counts[old as usize] > 0 {
+                self.duplicates -= 1;
+            }
+        }
+
+        (self.window.len() == self.len && self.duplicates == 0).then_some(self.position)
+    }
+}
+
+/// Iterator over every marker in a stream of bytes, see [find_markers]
+pub struct Markers<I> {
+    bytes: I,
+    detector: MarkerDetector,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let detector = &mut self.detector;
+        self.bytes.find_map(|byte| detector.push(byte))
+    }
+}
+
+/// Find the offset just past every marker of a given length in a stream
+/// of bytes. Markers can overlap, so a long enough run of distinct bytes
+/// produces a marker at every position
+///
+/// # Examples
+/// ```
+/// use aoc2022::day6::*;
+///
+/// let markers = find_markers("mjqjpqmgbljsphdztnvjfqwrcgsmlb".bytes(), 4);
+/// assert_eq!(markers.take(3).collect::<Vec<_>>(), vec![7, 8, 9]);
+///
+/// let markers = find_markers("aaaaaaa".bytes(), 2);
+/// assert_eq!(markers.count(), 0);
+/// ```
+pub fn find_markers<I: IntoIterator<Item = u8>>(bytes: I, len: usize) -> Markers<I::IntoIter> {
+    Markers {
+        bytes: bytes.into_iter(),
+        detector: MarkerDetector::new(len),
+    }
+}
+
+/// Like [find_markers], but reads the stream from anything readable, like
+/// a file or a socket. Read errors are passed along as they happen
+///
+/// # Examples
+/// ```
+/// use aoc2022::day6::*;
+///
+/// let signals = "bvwbjplbgvbhsrlpgdmjqwftvncz".as_bytes();
+/// let first = find_markers_in_reader(signals, 4).next();
+///
+/// assert_eq!(first.unwrap().unwrap(), 5);
+/// ```
+pub fn find_markers_in_reader<R: Read>(reader: R, len: usize) -> impl Iterator<Item = std::io::Result<usize>> {
+    let mut detector = MarkerDetector::new(len);
+
+    BufReader::new(reader)
+        .bytes()
+        .filter_map(move |byte| match byte {
+            Ok(byte) => detector.push(byte).map(Ok),
+            Err(err) => Some(Err(err)),
+        })
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Packet,
+    Message,
+}
+
+/// Ways a frame can be broken
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Corruption {
+    /// The marker was immediately followed by another marker, or by the end
+    /// of the stream
+    EmptyPayload,
+
+    /// A message turned up before any packet had started
+    OrphanMessage,
+}
+
+/// A single marker and everything after it, up to the next marker
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub kind: FrameKind,
+
+    /// Where the payload starts in the stream, i.e. the offset just past
+    /// the marker
+    pub offset: usize,
+    pub marker: &'a [u8],
+    pub payload: &'a [u8],
+    pub corruption: Option<Corruption>,
+}
+
+impl Frame<'_> {
+    pub fn is_corrupt(&self) -> bool {
+        self.corruption.is_some()
+    }
+}
+
+/// A datastream split up into frames
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transmission<'a> {
+    /// Whatever came before the first marker
+    pub preamble: &'a [u8],
+    pub frames: Vec<Frame<'a>>,
+}
+
+impl<'a> Transmission<'a> {
+    pub fn packets(&self) -> impl Iterator<Item = &Frame<'a>> {
+        self.frames
+            .iter()
+            .filter(|frame| frame.kind == FrameKind::Packet)
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &Frame<'a>> {
+        self.frames
+            .iter()
+            .filter(|frame| frame.kind == FrameKind::Message)
+    }
+}
+
+/// The elves' communication device. A datastream is a sequence of frames,
+/// each of which is a start-of-packet or start-of-message marker followed
+/// by a payload that runs up to the next marker. Markers are runs of
+/// distinct bytes, so a payload can't contain a long enough run of distinct
+/// bytes without it being mistaken for the next marker.
+///
+/// Any run of distinct bytes long enough to be the longer kind of marker
+/// is full of the shorter kind too, so the longer kind wins wherever they
+/// overlap (and if they're the same length, they're all messages)
+///
+/// # Examples
+/// ```
+/// use aoc2022::day6::*;
+///
+/// let device = Device::default();
+/// let transmission = device.decode(b"aabbabcdaaaabbbbabcdefghijklmnccdd");
+///
+/// assert_eq!(transmission.preamble, b"aabb");
+/// assert_eq!(transmission.frames, vec![
+///     Frame {
+///         kind: FrameKind::Packet,
+///         offset: 8,
+///         marker: b"abcd",
+///         payload: b"aaaabbbb",
+///         corruption: None,
+///     },
+///     Frame {
+///         kind: FrameKind::Message,
+///         offset: 30,
+///         marker: b"abcdefghijklmn",
+///         payload: b"ccdd",
+///         corruption: None,
+///     },
+/// ]);
+///
+/// // Back to back markers leave the first with nothing to say
+/// let transmission = device.decode(b"aaabcddefgh");
+/// assert_eq!(transmission.frames[0].corruption, Some(Corruption::EmptyPayload));
+/// assert_eq!(transmission.frames[1].marker, b"defg");
+///
+/// // Marker lengths are up to the device
+/// let device = Device::new(2, 3);
+/// let transmission = device.decode(b"abcaaaba");
+/// assert_eq!(transmission.frames[0].corruption, Some(Corruption::OrphanMessage));
+/// assert_eq!(transmission.frames[0].payload, b"aa");
+/// assert_eq!(transmission.frames[1].marker, b"ab");
+///
+/// // Markers the same length are always messages
+/// let transmission = Device::new(4, 4).decode(b"abcdaaaaefgh");
+/// assert!(transmission.frames.iter().all(|frame| frame.kind == FrameKind::Message));
+/// assert_eq!(transmission.frames.len(), 2);
+/// ```
+///
+/// ```should_panic
+/// aoc2022::day6::Device::new(0, 14);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    pub packet_marker_len: usize,
+    pub message_marker_len: usize,
 }
 
-impl<T> FunOverEngineering for &&[T] where T: Hash + Eq {
-    fn count_distinct(&self) -> usize {
-        HashSet::<_>::from_iter(**self).len()
+impl Default for Device {
+    fn default() -> Self {
+        Self { packet_marker_len: 4, message_marker_len: 14 }
+    }
+}
+
+impl Device {
+    /// # Panics
+    /// If either marker length is zero, see [MarkerDetector::new]
+    pub fn new(packet_marker_len: usize, message_marker_len: usize) -> Self {
+        assert!(
+            packet_marker_len > 0 && message_marker_len > 0,
+            "markers must be at least one byte long",
+        );
+
+        Self { packet_marker_len, message_marker_len }
+    }
+
+    fn marker_len(&self, kind: FrameKind) -> usize {
+        match kind {
+            FrameKind::Packet => self.packet_marker_len,
+            FrameKind::Message => self.message_marker_len,
+        }
+    }
+
+    /// Find where every marker starts, and what kind it is
+    fn find_all_markers(&self, stream: &[u8]) -> Vec<(usize, FrameKind)> {
+        // When they're the same length, every marker is found as the
+        // longer kind, so that has to be messages
+        let (shorter, longer) = if self.packet_marker_len <= self.message_marker_len {
+            (FrameKind::Packet, FrameKind::Message)
+        } else {
+            (FrameKind::Message, FrameKind::Packet)
+        };
+
+        let find_from = |from: usize, to: usize, kind: FrameKind| {
+            let len = self.marker_len(kind);
+            find_markers(stream[from..to].iter().copied(), len)
+                .next()
+                .map(|end| from + end - len)
+        };
+
+        let mut markers = vec![];
+        let mut cursor = 0;
+        let mut next_longer = find_from(0, stream.len(), longer);
+
+        // Shorter markers only count if they finish before the next longer
+        // one starts, otherwise they'd eat into it
+        loop {
+            let limit = next_longer.unwrap_or(stream.len());
+            let next = match (find_from(cursor, limit, shorter), next_longer) {
+                (Some(start), _) => (start, shorter),
+                (None, Some(start)) => (start, longer),
+                (None, None) => break,
+            };
+
+            cursor = next.0 + self.marker_len(next.1);
+            if next.1 == longer {
+                next_longer = find_from(cursor, stream.len(), longer);
+            }
+
+            markers.push(next);
+        }
+
+        markers
+    }
+
+    /// Split a datastream up into frames
+    ///
+    /// # Panics
+    /// If either marker length is zero, see [MarkerDetector::new]
+    pub fn decode<'a>(&self, stream: &'a [u8]) -> Transmission<'a> {
+        let markers = self.find_all_markers(stream);
+        let preamble = &stream[..markers.first().map_or(stream.len(), |&(start, _)| start)];
+        let mut frames = vec![];
+        let mut seen_packet = false;
+
+        for (idx, &(start, kind)) in markers.iter().enumerate() {
+            let offset = start + self.marker_len(kind);
+            let payload_end = markers.get(idx + 1).map_or(stream.len(), |&(next, _)| next);
+
+            seen_packet |= kind == FrameKind::Packet;
+            let corruption = if !seen_packet {
+                Some(Corruption::OrphanMessage)
+            } else if payload_end == offset {
+                Some(Corruption::EmptyPayload)
+            } else {
+                None
+            };
+
+            frames.push(Frame {
+                kind,
+                offset,
+                marker: &stream[start..offset],
+                payload: &stream[offset..payload_end],
+                corruption,
+            });
+        }
+
+        Transmission { preamble, frames }
     }
 }
 
@@ -21,12 +367,7 @@ impl<T> FunOverEngineering for &&[T] where T: Hash + Eq {
 /// assert_eq!(find_start_marker("aaaaaaa", 2), None);
 /// ```
 pub fn find_start_marker(signals: &str, len: usize) -> Option<usize> {
-    signals
-        .as_bytes()
-        .windows(len)
-        .enumerate()
-        .find(|(_, packet)| packet.count_distinct() == packet.len())
-        .map(|(idx, _)| idx + len)
+    find_markers(signals.bytes(), len).next()
 }
 
 #[cfg(test)]
@@ -44,4 +385,13 @@ mod answers {
         let input = include_str!("./input/day6.txt");
         assert_eq!(find_start_marker(input, 14), Some(3380));
     }
+
+    #[test]
+    fn decoding() {
+        let input = include_str!("./input/day6.txt");
+        let transmission = Device::default().decode(input.as_bytes());
+
+        assert_eq!(transmission.packets().next().map(|frame| frame.offset), Some(1909));
+        assert_eq!(transmission.messages().next().map(|frame| frame.offset), Some(3380));
+    }
 }
@@ -1,44 +1,751 @@
 //! Day 7 - No space left on device
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
 
-pub fn build_filesystem(terminal_output: &[&str]) -> HashMap<PathBuf, usize> {
-    let mut current_path = PathBuf::from("/");
-    let mut files = HashMap::new();
-
-    for line in terminal_output {
-        let mut tokens = line.split_whitespace();
-        match tokens.next() {
-            Some("$") => {
-                match tokens.next() {
-                    Some("ls") => (),
-                    Some("cd") => {
-                        match tokens.next() {
-                            Some("/") =>  { current_path = PathBuf::from("/"); },
-                            Some("..") => { current_path.pop(); },
-                            Some(dir) =>  { current_path.push(dir); },
-                            _ => panic!("malformed cd command"),
-                        };
-                    },
-                    command => panic!("Unrecognized input {command:?}"),
+/// Index of a node in a [FileSystem]
+pub type NodeId = usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dir {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub children: BTreeMap<String, NodeId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct File {
+    pub name: String,
+    pub parent: NodeId,
+    pub size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Dir(Dir),
+    File(File),
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        match self {
+            Node::Dir(dir) => &dir.name,
+            Node::File(file) => &file.name,
+        }
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        match self {
+            Node::Dir(dir) => dir.parent,
+            Node::File(file) => Some(file.parent),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Node::Dir(_))
+    }
+}
+
+/// A tree of directories and files, as pieced together from a terminal
+/// transcript. Nodes live in one big list and refer to each other by index,
+/// with the root directory always first. Children are always added after
+/// their parents, so a child's index is always bigger than its parent's
+///
+/// # Examples
+/// ```
+/// use aoc2022::day7::*;
+///
+/// let fs = FileSystem::from_transcript(SAMPLE);
+/// let a = fs.lookup("/a").unwrap();
+///
+/// assert_eq!(fs.total_size(fs.root()), 48381165);
+/// assert_eq!(fs.total_size(a), 94853);
+/// assert_eq!(fs.total_size(fs.lookup("/a/e").unwrap()), 584);
+/// assert_eq!(fs.total_size(fs.lookup("/d").unwrap()), 24933642);
+///
+/// let names = fs.children(a).map(|child| fs[child].name()).collect::<Vec<_>>();
+/// assert_eq!(names, vec!["e", "f", "g", "h.lst"]);
+///
+/// let largest = fs.largest_descendants(fs.root(), 2)
+///     .into_iter()
+///     .map(|(child, size)| (fs.path(child), size))
+///     .collect::<Vec<_>>();
+/// assert_eq!(largest, vec![("/d".into(), 24933642), ("/b.txt".into(), 14848514)]);
+/// # const SAMPLE: &[&str] = &[
+/// #     "$ cd /", "$ ls", "dir a", "14848514 b.txt", "8504156 c.dat", "dir d",
+/// #     "$ cd a", "$ ls", "dir e", "29116 f", "2557 g", "62596 h.lst",
+/// #     "$ cd e", "$ ls", "584 i", "$ cd ..", "$ cd ..", "$ cd d", "$ ls",
+/// #     "4060174 j", "8033020 d.log", "5626152 d.ext", "7214296 k",
+/// # ];
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Index<NodeId> for FileSystem {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Self::Output {
+        &self.nodes[id]
+    }
+}
+
+impl FileSystem {
+    /// A file system with nothing but an empty root directory
+    pub fn new() -> Self {
+        let root = Node::Dir(Dir { name: "/".into(), parent: None, children: BTreeMap::new() });
+        Self { nodes: vec![root] }
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    /// Every node in the file system, parents before children
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        0..self.nodes.len()
+    }
+
+    fn children_of(&self, dir: NodeId) -> &BTreeMap<String, NodeId> {
+        match &self.nodes[dir] {
+            Node::Dir(dir) => &dir.children,
+            Node::File(file) => panic!("{} is a file, not a directory", file.name),
+        }
+    }
+
+    fn insert(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let id = self.nodes.len();
+        let name = node.name().to_string();
+        self.nodes.push(node);
+
+        if let Node::Dir(dir) = &mut self.nodes[parent] {
+            dir.children.insert(name, id);
+        }
+
+        id
+    }
+
+    /// Find the directory with a given name inside another, creating it if
+    /// it's not there yet
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        match self.children_of(parent).get(name) {
+            Some(&existing) => existing,
+            None => self.insert(parent, Node::Dir(Dir {
+                name: name.into(),
+                parent: Some(parent),
+                children: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Add a file to a directory. Seeing the same file again (say, because
+    /// the directory was listed twice) just updates its size
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> NodeId {
+        match self.children_of(parent).get(name) {
+            Some(&existing) => {
+                if let Node::File(file) = &mut self.nodes[existing] {
+                    file.size = size;
                 }
+                existing
             },
-            Some("dir") => (),
-            Some(size) => {
-                let size: usize = size.parse().unwrap();
-                for ancestor in current_path.ancestors() {
-                    *files
-                        .entry(ancestor.to_owned())
-                        .or_insert(0) += size;
+            None => self.insert(parent, Node::File(File { name: name.into(), parent, size })),
+        }
+    }
+
+    /// The immediate children of a directory, in name order. Files don't
+    /// have any
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    ///
+    /// let fs = FileSystem::from_transcript(&["$ ls", "10 c", "dir a", "20 b"]);
+    /// let names = fs.children(fs.root()).map(|id| fs[id].name()).collect::<Vec<_>>();
+    /// assert_eq!(names, vec!["a", "b", "c"]);
+    ///
+    /// let file = fs.lookup("/c").unwrap();
+    /// assert_eq!(fs.children(file).count(), 0);
+    /// ```
+    pub fn children(&self, dir: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[dir] {
+            Node::Dir(dir) => Some(&dir.children),
+            Node::File(_) => None,
+        };
+
+        children
+            .into_iter()
+            .flat_map(|children| children.values().copied())
+    }
+
+    /// Everything underneath a directory, at any depth
+    pub fn descendants(&self, dir: NodeId) -> Vec<NodeId> {
+        let mut found = vec![];
+        let mut pending = vec![dir];
+
+        while let Some(next) = pending.pop() {
+            if self.nodes[next].is_dir() {
+                let children = self.children(next).collect::<Vec<_>>();
+                found.extend(&children);
+                pending.extend(children);
+            }
+        }
+
+        found
+    }
+
+    /// The total size of every node at once, indexed by node
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = self.nodes
+            .iter()
+            .map(|node| match node {
+                Node::File(file) => file.size,
+                Node::Dir(_) => 0,
+            })
+            .collect::<Vec<_>>();
+
+        // Children always come after their parents, so working backwards
+        // means every node's total is done before it's added to its parent
+        for id in (1..self.nodes.len()).rev() {
+            if let Some(parent) = self.nodes[id].parent() {
+                sizes[parent] += sizes[id];
+            }
+        }
+
+        sizes
+    }
+
+    /// The size of a file, or of everything inside a directory
+    pub fn total_size(&self, id: NodeId) -> usize {
+        match &self.nodes[id] {
+            Node::File(file) => file.size,
+            Node::Dir(_) => self
+                .children(id)
+                .map(|child| self.total_size(child))
+                .sum(),
+        }
+    }
+
+    /// The biggest `count` files and directories underneath a directory,
+    /// biggest first
+    pub fn largest_descendants(&self, dir: NodeId, count: usize) -> Vec<(NodeId, usize)> {
+        let sizes = self.sizes();
+        let mut descendants = self
+            .descendants(dir)
+            .into_iter()
+            .map(|id| (id, sizes[id]))
+            .collect::<Vec<_>>();
+
+        descendants.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        descendants.truncate(count);
+        descendants
+    }
+
+    /// The absolute path of a node
+    pub fn path(&self, id: NodeId) -> PathBuf {
+        let mut names = vec![];
+        let mut current = Some(id);
+
+        while let Some(node) = current {
+            names.push(self.nodes[node].name());
+            current = self.nodes[node].parent();
+        }
+
+        names.iter().rev().collect()
+    }
+
+    /// Find the node at an absolute path
+    pub fn lookup<P: AsRef<Path>>(&self, path: P) -> Option<NodeId> {
+        let mut current = self.root();
+
+        for component in path.as_ref().components() {
+            match component {
+                Component::RootDir => current = self.root(),
+                Component::CurDir => (),
+                Component::ParentDir => current = self.nodes[current].parent().unwrap_or(current),
+                Component::Normal(name) => {
+                    if !self.nodes[current].is_dir() {
+                        return None;
+                    }
+                    current = *self.children_of(current).get(name.to_str()?)?;
+                },
+                Component::Prefix(_) => return None,
+            }
+        }
+
+        Some(current)
+    }
+
+    /// Piece together a file system from the output of a terminal session,
+    /// ignoring anything that doesn't make sense. See [FileSystem::parse_transcript]
+    /// to find out what those things were
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    ///
+    /// // Listing the same directory twice doesn't count anything twice
+    /// let fs = FileSystem::from_transcript(&[
+    ///     "$ ls", "dir a", "100 b",
+    ///     "$ cd a", "$ ls", "50 c",
+    ///     "$ cd /", "$ ls", "dir a", "100 b",
+    /// ]);
+    ///
+    /// assert_eq!(fs.total_size(fs.root()), 150);
+    /// assert_eq!(fs.ids().count(), 4);
+    /// ```
+    pub fn from_transcript(terminal_output: &[&str]) -> Self {
+        Self::parse_transcript(terminal_output).0
+    }
+
+    /// Work out where a `cd` would take us, without actually going there
+    fn resolve(&self, cwd: NodeId, target: &str) -> Result<NodeId, (PathBuf, bool)> {
+        let mut current = if target.starts_with('/') { self.root() } else { cwd };
+
+        for segment in target.split('/') {
+            match segment {
+                "" | "." => (),
+                ".." => current = self.nodes[current].parent().unwrap_or(current),
+                name => match self.children_of(current).get(name) {
+                    Some(&child) if self.nodes[child].is_dir() => current = child,
+                    Some(_) => return Err((self.path(current).join(name), true)),
+                    None => return Err((self.path(current).join(name), false)),
+                },
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Like [resolve](Self::resolve), but creates any directories that
+    /// aren't there yet. Fails with the path of the first segment that turns
+    /// out to be a file
+    fn create_path(&mut self, cwd: NodeId, target: &str) -> Result<NodeId, PathBuf> {
+        let mut current = if target.starts_with('/') { self.root() } else { cwd };
+
+        for segment in target.split('/') {
+            match segment {
+                "" | "." => (),
+                ".." => current = self.nodes[current].parent().unwrap_or(current),
+                name => match self.children_of(current).get(name) {
+                    Some(&child) if !self.nodes[child].is_dir() => return Err(self.path(current).join(name)),
+                    _ => current = self.add_dir(current, name),
+                },
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Piece together a file system from the output of a terminal session,
+    /// noting anything that doesn't add up along the way. Understands `cd`
+    /// (to absolute or relative paths with any number of segments), `ls`,
+    /// `pwd`, and comments starting with `#`
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    ///
+    /// let (fs, issues) = FileSystem::parse_transcript(&[
+    ///     "# let's have a look around",
+    ///     "$ ls",
+    ///     "dir a",
+    ///     "100 b",
+    ///     "$ cd a/c",
+    ///     "$ pwd",
+    ///     "/a/c",
+    ///     "$ ls",
+    ///     "50 d",
+    ///     "$ cd ../../",
+    ///     "$ ls",
+    ///     "dir a",
+    ///     "120 b",
+    ///     "$ cd b",
+    ///     "$ rm -rf /",
+    /// ]);
+    ///
+    /// assert_eq!(fs.total_size(fs.lookup("/a/c").unwrap()), 50);
+    /// assert_eq!(fs.total_size(fs.root()), 170);
+    /// assert_eq!(issues, vec![
+    ///     Issue::UnlistedDirectory { line: 5, path: "/a/c".into() },
+    ///     Issue::SizeMismatch { line: 13, path: "/b".into(), previous: 100, listed: 120 },
+    ///     Issue::NotADirectory { line: 14, path: "/b".into() },
+    ///     Issue::UnknownCommand { line: 15, command: "rm".into() },
+    /// ]);
+    /// ```
+    pub fn parse_transcript(terminal_output: &[&str]) -> (Self, Vec<Issue>) {
+        let mut fs = Self::new();
+        let mut issues = vec![];
+        let mut cwd = fs.root();
+        let mut mode = Mode::Idle;
+
+        for (idx, line) in terminal_output.iter().enumerate() {
+            let line_number = idx + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if let Some(command) = trimmed.strip_prefix('$') {
+                let mut args = command.split_whitespace();
+                mode = Mode::Idle;
+
+                match args.next() {
+                    Some("ls") => {
+                        if let Some(arg) = args.next() {
+                            issues.push(Issue::UnsupportedArgument { line: line_number, argument: arg.into() });
+                        } else {
+                            mode = Mode::Listing;
+                        }
+                    },
+                    Some("pwd") => mode = Mode::Pwd,
+                    Some("cd") => match args.next() {
+                        Some(target) => match fs.resolve(cwd, target) {
+                            Ok(dir) => cwd = dir,
+                            Err((path, true)) => issues.push(Issue::NotADirectory { line: line_number, path }),
+                            Err((path, false)) => {
+                                // Nobody's seen this directory, but it must be
+                                // there if we managed to cd into it
+                                issues.push(Issue::UnlistedDirectory { line: line_number, path });
+                                match fs.create_path(cwd, target) {
+                                    Ok(dir) => cwd = dir,
+                                    Err(path) => issues.push(Issue::NotADirectory { line: line_number, path }),
+                                }
+                            },
+                        },
+                        None => issues.push(Issue::MissingArgument { line: line_number, command: "cd".into() }),
+                    },
+                    Some(command) => issues.push(Issue::UnknownCommand { line: line_number, command: command.into() }),
+                    None => issues.push(Issue::MissingCommand { line: line_number }),
                 }
 
+                continue;
+            }
+
+            match mode {
+                Mode::Idle => issues.push(Issue::UnexpectedOutput { line: line_number, text: trimmed.into() }),
+                Mode::Pwd => {
+                    let expected = fs.path(cwd);
+                    if Path::new(trimmed) != expected {
+                        issues.push(Issue::WrongDirectory { line: line_number, expected, reported: trimmed.into() });
+                    }
+                    mode = Mode::Idle;
+                },
+                Mode::Listing => {
+                    // File names might have spaces in them, so take everything after the first token
+                    let Some((kind, name)) = trimmed.split_once(char::is_whitespace) else {
+                        issues.push(Issue::MalformedEntry { line: line_number, text: trimmed.into() });
+                        continue;
+                    };
+
+                    let name = name.trim();
+                    let existing = fs.children_of(cwd).get(name).copied();
+                    let path = fs.path(cwd).join(name);
+
+                    match (kind, existing.map(|id| &fs.nodes[id])) {
+                        ("dir", None | Some(Node::Dir(_))) => {
+                            fs.add_dir(cwd, name);
+                        },
+                        ("dir", Some(Node::File(_))) => {
+                            issues.push(Issue::TypeConflict { line: line_number, path });
+                        },
+                        (size, existing) => match (size.parse(), existing) {
+                            (Err(_), _) => issues.push(Issue::MalformedEntry { line: line_number, text: trimmed.into() }),
+                            (Ok(_), Some(Node::Dir(_))) => issues.push(Issue::TypeConflict { line: line_number, path }),
+                            (Ok(size), existing) => {
+                                if let Some(Node::File(file)) = existing {
+                                    if file.size != size {
+                                        issues.push(Issue::SizeMismatch { line: line_number, path, previous: file.size, listed: size });
+                                    }
+                                }
+                                fs.add_file(cwd, name, size);
+                            },
+                        },
+                    }
+                },
+            }
+        }
+
+        (fs, issues)
+    }
+}
+
+/// What we're expecting to see next in a transcript
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Idle,
+    Listing,
+    Pwd,
+}
+
+/// Something in a terminal transcript that doesn't add up. Line numbers
+/// are 1-based
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// A `$` with nothing after it
+    MissingCommand { line: usize },
+
+    /// A command other than `cd`, `ls` or `pwd`
+    UnknownCommand { line: usize, command: String },
+
+    /// A command that needs an argument didn't get one
+    MissingArgument { line: usize, command: String },
+
+    /// A command got an argument we don't know how to handle
+    UnsupportedArgument { line: usize, argument: String },
+
+    /// Output that didn't come from any command we understand
+    UnexpectedOutput { line: usize, text: String },
+
+    /// A line of `ls` output that isn't `dir <name>` or `<size> <name>`
+    MalformedEntry { line: usize, text: String },
+
+    /// A file was listed with a different size than it had before
+    SizeMismatch { line: usize, path: PathBuf, previous: usize, listed: usize },
+
+    /// Something was listed as a file in one place and a directory in another
+    TypeConflict { line: usize, path: PathBuf },
+
+    /// A `cd` into a directory that hadn't been listed yet
+    UnlistedDirectory { line: usize, path: PathBuf },
+
+    /// A `cd` into something that's known to be a file
+    NotADirectory { line: usize, path: PathBuf },
+
+    /// `pwd` says we're somewhere other than where we think we are
+    WrongDirectory { line: usize, expected: PathBuf, reported: PathBuf },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::MissingCommand { line } =>
+                write!(f, "line {line}: prompt with no command"),
+            Issue::UnknownCommand { line, command } =>
+                write!(f, "line {line}: unknown command {command:?}"),
+            Issue::MissingArgument { line, command } =>
+                write!(f, "line {line}: {command} needs an argument"),
+            Issue::UnsupportedArgument { line, argument } =>
+                write!(f, "line {line}: unsupported argument {argument:?}"),
+            Issue::UnexpectedOutput { line, text } =>
+                write!(f, "line {line}: unexpected output {text:?}"),
+            Issue::MalformedEntry { line, text } =>
+                write!(f, "line {line}: can't make sense of listing {text:?}"),
+            Issue::SizeMismatch { line, path, previous, listed } =>
+                write!(f, "line {line}: {} was {previous} bytes, now listed as {listed}", path.display()),
+            Issue::TypeConflict { line, path } =>
+                write!(f, "line {line}: {} is listed as both a file and a directory", path.display()),
+            Issue::UnlistedDirectory { line, path } =>
+                write!(f, "line {line}: cd into {}, which was never listed", path.display()),
+            Issue::NotADirectory { line, path } =>
+                write!(f, "line {line}: cd into {}, which is a file", path.display()),
+            Issue::WrongDirectory { line, expected, reported } =>
+                write!(f, "line {line}: expected to be in {}, but pwd says {}", expected.display(), reported.display()),
+        }
+    }
+}
+
+/// Whether a name matches a shell-style pattern, where `*` matches any run
+/// of characters and `?` matches any single character
+///
+/// # Examples
+/// ```
+/// use aoc2022::day7::*;
+///
+/// assert!(glob_match("*.log", "d.log"));
+/// assert!(glob_match("?.*", "b.txt"));
+/// assert!(!glob_match("*.log", "d.ext"));
+/// ```
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // Greedy matching, backtracking to the most recent star on a mismatch
+    let (mut p, mut n) = (0, 0);
+    let mut last_star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p, n));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match last_star {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    last_star = Some((star, matched + 1));
+                },
+                None => return false,
             },
-            unknown => panic!("Unrecognized input {unknown:?}"),
         }
     }
 
-    files
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// What to delete to free up enough space, see [FileSystem::free_up_space]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cleanup {
+    /// There's already enough space
+    NotNeeded,
+
+    /// Delete this directory, which will free up this much space
+    Delete(NodeId, usize),
+
+    /// Even deleting everything wouldn't free up enough space
+    Impossible,
+}
+
+impl FileSystem {
+    /// The total size of a directory and every directory underneath it, like
+    /// `du`. Directories are listed in path order
+    pub fn du(&self, dir: NodeId) -> Vec<(PathBuf, usize)> {
+        let sizes = self.sizes();
+        let mut usage = std::iter::once(dir)
+            .chain(self.descendants(dir))
+            .filter(|&id| self.nodes[id].is_dir())
+            .map(|id| (self.path(id), sizes[id]))
+            .collect::<Vec<_>>();
+
+        usage.sort_unstable();
+        usage
+    }
+
+    /// Draw everything under a node the same way the puzzle does
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    ///
+    /// let fs = FileSystem::from_transcript(&[
+    ///     "$ cd /", "$ ls", "dir a", "14848514 b.txt",
+    ///     "$ cd a", "$ ls", "29116 f",
+    /// ]);
+    ///
+    /// assert_eq!(fs.tree(fs.root()), concat!(
+    ///     "- / (dir)\n",
+    ///     "  - a (dir)\n",
+    ///     "    - f (file, size=29116)\n",
+    ///     "  - b.txt (file, size=14848514)\n",
+    /// ));
+    /// ```
+    pub fn tree(&self, id: NodeId) -> String {
+        let mut drawing = String::new();
+        let mut pending = vec![(id, 0)];
+
+        while let Some((next, depth)) = pending.pop() {
+            let indent = "  ".repeat(depth);
+            match &self.nodes[next] {
+                Node::Dir(dir) => {
+                    drawing += &format!("{indent}- {} (dir)\n", dir.name);
+
+                    let children = self.children(next).collect::<Vec<_>>();
+                    pending.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+                },
+                Node::File(file) => {
+                    drawing += &format!("{indent}- {} (file, size={})\n", file.name, file.size);
+                },
+            }
+        }
+
+        drawing
+    }
+
+    /// Everything underneath a directory that matches a predicate, which
+    /// gets to look at each node along with its total size
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    /// use std::path::PathBuf;
+    ///
+    /// let fs = FileSystem::from_transcript(&[
+    ///     "$ ls", "dir a", "14848514 b.txt", "$ cd a", "$ ls", "29116 f", "2557 g",
+    /// ]);
+    ///
+    /// let small = fs.find(fs.root(), |_, size| size < 100000);
+    /// let paths = small.into_iter().map(|id| fs.path(id)).collect::<Vec<_>>();
+    /// assert_eq!(paths, ["/a", "/a/f", "/a/g"].map(PathBuf::from));
+    /// ```
+    pub fn find<F>(&self, dir: NodeId, predicate: F) -> Vec<NodeId>
+    where
+        F: Fn(&Node, usize) -> bool,
+    {
+        let sizes = self.sizes();
+        let mut found = self
+            .descendants(dir)
+            .into_iter()
+            .filter(|&id| predicate(&self.nodes[id], sizes[id]))
+            .collect::<Vec<_>>();
+
+        found.sort_unstable();
+        found
+    }
+
+    /// Everything underneath a directory whose name matches a shell-style
+    /// pattern, see [glob_match]
+    pub fn find_by_name(&self, dir: NodeId, pattern: &str) -> Vec<NodeId> {
+        self.find(dir, |node, _| glob_match(pattern, node.name()))
+    }
+
+    /// Figure out the smallest single directory to delete so that there's at
+    /// least `required` space free on a disk of size `capacity`
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day7::*;
+    ///
+    /// let fs = FileSystem::from_transcript(&[
+    ///     "$ ls", "dir a", "dir b", "100 c", "$ cd a", "$ ls", "200 d", "$ cd ..", "$ cd b", "$ ls", "300 e",
+    /// ]);
+    ///
+    /// assert_eq!(fs.free_up_space(1000, 100), Cleanup::NotNeeded);
+    /// assert_eq!(fs.free_up_space(1000, 500), Cleanup::Delete(fs.lookup("/a").unwrap(), 200));
+    /// assert_eq!(fs.free_up_space(1000, 700), Cleanup::Delete(fs.lookup("/b").unwrap(), 300));
+    /// assert_eq!(fs.free_up_space(1000, 1001), Cleanup::Impossible);
+    /// ```
+    pub fn free_up_space(&self, capacity: usize, required: usize) -> Cleanup {
+        let sizes = self.sizes();
+        let free = capacity.saturating_sub(sizes[self.root()]);
+        if free >= required {
+            return Cleanup::NotNeeded;
+        }
+
+        let needed = required - free;
+        self.ids()
+            .filter(|&id| self.nodes[id].is_dir() && sizes[id] >= needed)
+            .min_by_key(|&id| sizes[id])
+            .map_or(Cleanup::Impossible, |id| Cleanup::Delete(id, sizes[id]))
+    }
+}
+
+impl std::fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tree(self.root()))
+    }
+}
+
+/// Figure out the total size of every directory from the output of a
+/// terminal session, keyed by the directory's absolute path
+pub fn build_filesystem(terminal_output: &[&str]) -> HashMap<PathBuf, usize> {
+    let fs = FileSystem::from_transcript(terminal_output);
+    let sizes = fs.sizes();
+
+    fs.ids()
+        .filter(|&id| fs[id].is_dir())
+        .map(|id| (fs.path(id), sizes[id]))
+        .collect()
 }
 
 #[cfg(test)]
@@ -73,4 +780,52 @@ mod answers {
 
         assert_eq!(smallest, Some(&1300850));
     }
+
+    #[test]
+    fn shell_queries() {
+        let input = include_str!("./input/day7.txt")
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        let fs = FileSystem::from_transcript(&input);
+
+        let little_guys: usize = fs
+            .du(fs.root())
+            .iter()
+            .map(|&(_, size)| size)
+            .filter(|&size| size <= 100000)
+            .sum();
+        assert_eq!(little_guys, 1644735);
+
+        match fs.free_up_space(70000000, 30000000) {
+            Cleanup::Delete(_, size) => assert_eq!(size, 1300850),
+            other => panic!("Expected to delete something, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn consistency() {
+        let input = include_str!("./input/day7.txt")
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        let (_, issues) = FileSystem::parse_transcript(&input);
+
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn cd_into_unlisted_paths() {
+        // Trailing and doubled slashes don't send us back to the root
+        let (fs, _) = FileSystem::parse_transcript(&["$ cd /x//y/", "$ ls", "10 f"]);
+        assert!(fs.lookup("/x/y/f").is_some());
+
+        // Going through a file is an issue, not a panic
+        let (fs, issues) = FileSystem::parse_transcript(&["$ ls", "10 b", "$ cd x/../b", "$ ls", "5 c"]);
+        assert_eq!(issues, vec![
+            Issue::UnlistedDirectory { line: 3, path: "/x".into() },
+            Issue::NotADirectory { line: 3, path: "/b".into() },
+        ]);
+        assert_eq!(fs.total_size(fs.root()), 15);
+    }
 }
@@ -1,6 +1,7 @@
 //! Day 8 - Treetop Tree House
 
 use grid::Grid;
+use std::io::{self, Write};
 
 #[derive(Clone, Debug)]
 pub struct Node {
@@ -15,6 +16,14 @@ impl Node {
             visibility: None,
         }
     }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn visibility(&self) -> Option<bool> {
+        self.visibility
+    }
 }
 
 impl std::cmp::PartialEq for Node {
@@ -47,25 +56,145 @@ pub fn with_visibility(grid: &Grid<Node>, vis: Option<bool>) -> usize {
         .count()
 }
 
-fn assess_tree(tallest: Option<u32>, tree: &mut Node) -> Option<u32> {
-    match tallest {
-        // If there's no previous tallest tree, this one must be tallest
-        None => {
-            tree.visibility = Some(true);
-            Some(tree.height)
-        },
-        Some(tallest) => {
-            // If we already know the tree is visible, we don't care if whether
-            // it's visible or not from some other direction. So only bother if
-            // we haven't checked this tree, or if we haven't found it to be
-            // visible from some other direction yet
-            if tree.visibility != Some(true) {
-                tree.visibility = Some(tree.height > tallest);
+/// One of something for each direction you can look from a tree
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sides<T> {
+    pub up: T,
+    pub down: T,
+    pub left: T,
+    pub right: T,
+}
+
+impl<T: Copy> Sides<T> {
+    pub fn iter(&self) -> impl Iterator<Item = T> {
+        [self.up, self.down, self.left, self.right].into_iter()
+    }
+}
+
+/// Everything there is to know about the view from (and of) a tree
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeView {
+    /// Whether the tree can be seen from outside the forest, looking in
+    /// from each edge
+    pub visible_from: Sides<bool>,
+
+    /// How many trees can be seen from the top of this one, looking in
+    /// each direction
+    pub viewing_distance: Sides<usize>,
+}
+
+impl TreeView {
+    pub fn is_visible(&self) -> bool {
+        self.visible_from.iter().any(|visible| visible)
+    }
+
+    pub fn scenic_score(&self) -> usize {
+        self.viewing_distance.iter().product()
+    }
+}
+
+/// For every tree in a line, look back towards the start of the line and
+/// figure out whether it can be seen from that end, and how far it can see.
+///
+/// Keeps a stack of the trees that could still block the view of trees
+/// further along. A tree hides everything on the stack shorter than itself
+/// from then on, so those get popped, and whatever is left on top is the
+/// nearest tree at least as tall. Every tree is pushed and popped at most
+/// once, so the whole line takes linear time
+fn look_back(heights: &[u32]) -> Vec<(bool, usize)> {
+    let mut blockers: Vec<usize> = vec![];
+
+    heights
+        .iter()
+        .enumerate()
+        .map(|(idx, &height)| {
+            while blockers.last().is_some_and(|&blocker| heights[blocker] < height) {
+                blockers.pop();
             }
 
-            Some(tallest.max(tree.height))
+            let view = match blockers.last() {
+                Some(&blocker) => (false, idx - blocker),
+                None => (true, idx),
+            };
+
+            blockers.push(idx);
+            view
+        })
+        .collect()
+}
+
+/// Given a heightmap of the trees, work out the view from every tree in
+/// every direction, in time proportional to the size of the forest
+///
+/// # Examples
+///
+/// ```
+/// use aoc2022::day8::*;
+///
+/// let sample = load_grid(vec![
+///     "30373",
+///     "25512",
+///     "65332",
+///     "33549",
+///     "35390",
+/// ]);
+///
+/// let views = survey_forest(&sample);
+///
+/// // The middle 5 in the second row
+/// let view = views[1][2];
+/// assert_eq!(view.visible_from, Sides { up: true, down: false, left: false, right: true });
+/// assert_eq!(view.viewing_distance, Sides { up: 1, down: 2, left: 1, right: 2 });
+/// assert_eq!(view.scenic_score(), 4);
+///
+/// // The 5 in the middle of the fourth row
+/// assert_eq!(views[3][2].scenic_score(), 8);
+/// assert_eq!(views.iter().filter(|view| view.is_visible()).count(), 21);
+/// ```
+pub fn survey_forest(heightmap: &Grid<Node>) -> Grid<TreeView> {
+    let (rows, cols) = heightmap.size();
+    let mut views = Grid::<TreeView>::new(rows, cols);
+
+    for row in 0..rows {
+        let mut heights = heightmap.iter_row(row).map(|node| node.height).collect::<Vec<_>>();
+        for (col, (visible, distance)) in look_back(&heights).into_iter().enumerate() {
+            views[row][col].visible_from.left = visible;
+            views[row][col].viewing_distance.left = distance;
+        }
+
+        heights.reverse();
+        for (col, (visible, distance)) in look_back(&heights).into_iter().rev().enumerate() {
+            views[row][col].visible_from.right = visible;
+            views[row][col].viewing_distance.right = distance;
         }
     }
+
+    for col in 0..cols {
+        let mut heights = heightmap.iter_col(col).map(|node| node.height).collect::<Vec<_>>();
+        for (row, (visible, distance)) in look_back(&heights).into_iter().enumerate() {
+            views[row][col].visible_from.up = visible;
+            views[row][col].viewing_distance.up = distance;
+        }
+
+        heights.reverse();
+        for (row, (visible, distance)) in look_back(&heights).into_iter().rev().enumerate() {
+            views[row][col].visible_from.down = visible;
+            views[row][col].viewing_distance.down = distance;
+        }
+    }
+
+    views
+}
+
+/// Given a heightmap of the trees, figure out the scenic score of every tree
+pub fn scenic_scores(heightmap: &Grid<Node>) -> Grid<usize> {
+    let views = survey_forest(heightmap);
+    let scores = views
+        .iter()
+        .map(TreeView::scenic_score)
+        .collect();
+
+    Grid::from_vec(scores, views.cols())
 }
 
 /// Given a heightmap of the trees, figure out which are visible and which are
@@ -88,14 +217,10 @@ fn assess_tree(tallest: Option<u32>, tree: &mut Node) -> Option<u32> {
 /// assert_eq!(with_visibility(&sample, Some(true)), 21);
 /// ```
 pub fn categorize_trees(heightmap: &mut Grid<Node>) {
-    for idx in 0..heightmap.rows() {
-        heightmap.iter_row_mut(idx).fold(None, assess_tree);
-        heightmap.iter_row_mut(idx).rfold(None, assess_tree);
-    }
+    let views = survey_forest(heightmap);
 
-    for idx in 0..heightmap.cols() {
-        heightmap.iter_col_mut(idx).fold(None, assess_tree);
-        heightmap.iter_col_mut(idx).rfold(None, assess_tree);
+    for (node, view) in heightmap.iter_mut().zip(views.iter()) {
+        node.visibility = Some(view.is_visible());
     }
 }
 
@@ -117,34 +242,185 @@ pub fn categorize_trees(heightmap: &mut Grid<Node>) {
 /// assert_eq!(best_scenic_score(&sample), 8);
 /// ```
 pub fn best_scenic_score(heightmap: &Grid<Node>) -> usize {
-    let (rows, cols) = heightmap.size();
-    let mut best_score = 1;
-
-    // Visit every node, cast rays in each cardinal direction, and count
-    // how many trees we can see, noting that since anything on the periphery
-    // will have at least one score of zero, there's no point in calculating them
-    for row in 1..rows-1 {
-        for col in 1..cols-1 {
-            if let Some(this_node) = heightmap.get(row, col) {
-                let this_row = heightmap.iter_row(row).collect::<Vec<_>>();
-                let this_col = heightmap.iter_col(col).collect::<Vec<_>>();
-
-                let (left, right) = this_row.split_at(col);
-                let (up, down) = this_col.split_at(row);
-
-                // Mixing indexes and offsets is always a blast -_-
-                let right_score = right[1..].iter().position(|&x| x >= this_node).map_or(right.len() - 1, |p| p + 1);
-                let left_score = left.iter().rposition(|&x| x >= this_node).map_or(left.len(), |p| left.len() - p);
-                let down_score = down[1..].iter().position(|&x| x >= this_node).map_or(down.len() - 1, |p| p + 1);
-                let up_score = up.iter().rposition(|&x| x >= this_node).map_or(up.len(), |p| up.len() - p);
-
-                // No, I don't care about overflow
-                best_score = best_score.max(right_score * left_score * down_score * up_score);
-            }
+    scenic_scores(heightmap)
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+/// Where the best treehouse would go, as `(row, col, score)`. Ties go to
+/// whichever comes first, reading left to right and top to bottom
+///
+/// # Examples
+/// ```
+/// use aoc2022::day8::*;
+///
+/// let sample = load_grid(vec!["30373", "25512", "65332", "33549", "35390"]);
+/// assert_eq!(best_treehouse(&sample), Some((3, 2, 8)));
+/// ```
+pub fn best_treehouse(heightmap: &Grid<Node>) -> Option<(usize, usize, usize)> {
+    let scores = scenic_scores(heightmap);
+    let cols = scores.cols();
+
+    scores
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|&(_, &score)| score)
+        .map(|(idx, &score)| (idx / cols, idx % cols, score))
+}
+
+/// What a heatmap shows for each tree
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeatmapView {
+    Height,
+
+    /// 1 if the tree can be seen from outside the forest, 0 if not
+    Visibility,
+    ScenicScore,
+}
+
+/// Renders a forest as a heatmap, either as CSV or as a PPM image
+///
+/// # Examples
+/// ```
+/// use aoc2022::day8::*;
+///
+/// let sample = load_grid(vec!["30373", "25512", "65332", "33549", "35390"]);
+///
+/// let mut csv = vec![];
+/// Heatmap::new(HeatmapView::ScenicScore).write_csv(&sample, &mut csv).unwrap();
+/// assert_eq!(String::from_utf8(csv).unwrap(), concat!(
+///     "0,0,0,0,0\n",
+///     "0,1,4,1,0\n",
+///     "0,6,1,2,0\n",
+///     "0,1,8,3,0\n",
+///     "0,0,0,0,0\n",
+/// ));
+///
+/// let mut csv = vec![];
+/// let heatmap = Heatmap { view: HeatmapView::Visibility, scale: 1, mark_best: true };
+/// heatmap.write_csv(&sample, &mut csv).unwrap();
+/// assert_eq!(String::from_utf8(csv).unwrap().lines().nth(3), Some("1,0,1*,0,1"));
+///
+/// let mut ppm = vec![];
+/// let heatmap = Heatmap { view: HeatmapView::Height, scale: 4, mark_best: true };
+/// heatmap.write_ppm(&sample, &mut ppm).unwrap();
+/// assert!(ppm.starts_with(b"P6\n20 20\n255\n"));
+/// assert_eq!(ppm.len(), b"P6\n20 20\n255\n".len() + 20 * 20 * 3);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Heatmap {
+    pub view: HeatmapView,
+
+    /// How many pixels along each side of a tree in the image
+    pub scale: usize,
+
+    /// Whether to highlight the best treehouse location. It's drawn in red
+    /// in the image, and has a `*` after its value in the CSV
+    pub mark_best: bool,
+}
+
+/// Colour used to mark the best treehouse location in images
+const BEST_COLOUR: [u8; 3] = [255, 0, 0];
+
+/// Colours at the bottom and top of the heatmap's scale
+const COLD: [u8; 3] = [13, 8, 135];
+const HOT: [u8; 3] = [240, 249, 33];
+
+impl Heatmap {
+    pub fn new(view: HeatmapView) -> Self {
+        Self { view, scale: 1, mark_best: false }
+    }
+
+    /// The value shown for each tree
+    pub fn values(&self, heightmap: &Grid<Node>) -> Grid<usize> {
+        let values = match self.view {
+            HeatmapView::Height => heightmap
+                .iter()
+                .map(|node| node.height as usize)
+                .collect(),
+            HeatmapView::Visibility => survey_forest(heightmap)
+                .iter()
+                .map(|view| view.is_visible() as usize)
+                .collect(),
+            HeatmapView::ScenicScore => scenic_scores(heightmap).into_vec(),
+        };
+
+        Grid::from_vec(values, heightmap.cols())
+    }
+
+    fn best(&self, heightmap: &Grid<Node>) -> Option<(usize, usize)> {
+        self.mark_best
+            .then(|| best_treehouse(heightmap))
+            .flatten()
+            .map(|(row, col, _)| (row, col))
+    }
+
+    /// Write one line per row of trees, with values separated by commas
+    pub fn write_csv<W: Write>(&self, heightmap: &Grid<Node>, mut writer: W) -> io::Result<()> {
+        let values = self.values(heightmap);
+        let best = self.best(heightmap);
+
+        for row in 0..values.rows() {
+            let line = values
+                .iter_row(row)
+                .enumerate()
+                .map(|(col, value)| {
+                    let mark = if best == Some((row, col)) { "*" } else { "" };
+                    format!("{value}{mark}")
+                })
+                .collect::<Vec<_>>();
+
+            writeln!(writer, "{}", line.join(","))?;
         }
+
+        Ok(())
     }
 
-    best_score
+    /// Write a binary PPM (P6) image, shading each tree from dark blue for
+    /// the lowest value up to yellow for the highest
+    pub fn write_ppm<W: Write>(&self, heightmap: &Grid<Node>, mut writer: W) -> io::Result<()> {
+        let values = self.values(heightmap);
+        let best = self.best(heightmap);
+        let scale = self.scale.max(1);
+        let lowest = values.iter().copied().min().unwrap_or(0);
+        let highest = values.iter().copied().max().unwrap_or(0);
+
+        let colour_of = |row: usize, col: usize| {
+            if best == Some((row, col)) {
+                return BEST_COLOUR;
+            }
+
+            let value = values[row][col];
+            let fraction = if highest > lowest {
+                (value - lowest) as f64 / (highest - lowest) as f64
+            } else {
+                0.0
+            };
+
+            std::array::from_fn(|channel| {
+                let (cold, hot) = (COLD[channel] as f64, HOT[channel] as f64);
+                (cold + (hot - cold) * fraction).round() as u8
+            })
+        };
+
+        write!(writer, "P6\n{} {}\n255\n", values.cols() * scale, values.rows() * scale)?;
+
+        for row in 0..values.rows() {
+            let line = (0..values.cols())
+                .flat_map(|col| std::iter::repeat(colour_of(row, col)).take(scale))
+                .flatten()
+                .collect::<Vec<_>>();
+
+            for _ in 0..scale {
+                writer.write_all(&line)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
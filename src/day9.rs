@@ -1,124 +1,453 @@
 //! Day 9 - Rope Bridge
 
+use crate::line_error::LineError;
 use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
 
-pub type Instruction = (char, usize,);
+/// Which way to move the head of the rope, and how many steps to take.
+/// Diagonal moves change both coordinates at once
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Up(usize),
+    Down(usize),
+    Left(usize),
+    Right(usize),
+    UpLeft(usize),
+    UpRight(usize),
+    DownLeft(usize),
+    DownRight(usize),
+}
+
+impl Instruction {
+    /// How far the head moves in a single step
+    pub fn delta(&self) -> Position {
+        match self {
+            Self::Up(_) => (0, 1),
+            Self::Down(_) => (0, -1),
+            Self::Left(_) => (-1, 0),
+            Self::Right(_) => (1, 0),
+            Self::UpLeft(_) => (-1, 1),
+            Self::UpRight(_) => (1, 1),
+            Self::DownLeft(_) => (-1, -1),
+            Self::DownRight(_) => (1, -1),
+        }
+    }
+
+    /// How many steps to take
+    pub fn count(&self) -> usize {
+        match *self {
+            Self::Up(count) | Self::Down(count) | Self::Left(count) | Self::Right(count) |
+            Self::UpLeft(count) | Self::UpRight(count) | Self::DownLeft(count) | Self::DownRight(count) => count,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Up(_) => "U",
+            Self::Down(_) => "D",
+            Self::Left(_) => "L",
+            Self::Right(_) => "R",
+            Self::UpLeft(_) => "UL",
+            Self::UpRight(_) => "UR",
+            Self::DownLeft(_) => "DL",
+            Self::DownRight(_) => "DR",
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.code(), self.count())
+    }
+}
+
+/// Reasons a single instruction can't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstructionError {
+    /// The line is empty
+    Missing,
+
+    /// Not one of `U`, `D`, `L`, `R`, `UL`, `UR`, `DL` or `DR`
+    UnknownDirection(String),
+
+    /// There's a direction but no step count
+    MissingCount,
+
+    /// The step count isn't a non-negative number
+    BadCount(String),
+
+    /// There's something after the step count
+    TrailingInput(String),
+}
+
+impl Display for InstructionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no instruction"),
+            Self::UnknownDirection(direction) => write!(f, "unknown direction {direction:?}"),
+            Self::MissingCount => write!(f, "missing step count"),
+            Self::BadCount(count) => write!(f, "bad step count {count:?}"),
+            Self::TrailingInput(rest) => write!(f, "unexpected {rest:?} after the step count"),
+        }
+    }
+}
+
+impl std::error::Error for InstructionError {}
+
+/// An instruction that couldn't be parsed
+pub type ParseError = LineError<InstructionError>;
+
+impl FromStr for Instruction {
+    type Err = InstructionError;
+
+    /// # Examples
+    /// ```
+    /// use aoc2022::day9::*;
+    ///
+    /// assert_eq!("R 4".parse(), Ok(Instruction::Right(4)));
+    /// assert_eq!("DL 2".parse(), Ok(Instruction::DownLeft(2)));
+    /// assert_eq!("X 2".parse::<Instruction>(), Err(InstructionError::UnknownDirection("X".into())));
+    /// assert_eq!("U -1".parse::<Instruction>(), Err(InstructionError::BadCount("-1".into())));
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let direction = tokens.next().ok_or(InstructionError::Missing)?;
+        let count = tokens.next().ok_or(InstructionError::MissingCount)?;
+        let count = count.parse().map_err(|_| InstructionError::BadCount(count.into()))?;
+
+        if let Some(rest) = tokens.next() {
+            return Err(InstructionError::TrailingInput(rest.into()));
+        }
+
+        Ok(match direction {
+            "U" => Self::Up(count),
+            "D" => Self::Down(count),
+            "L" => Self::Left(count),
+            "R" => Self::Right(count),
+            "UL" => Self::UpLeft(count),
+            "UR" => Self::UpRight(count),
+            "DL" => Self::DownLeft(count),
+            "DR" => Self::DownRight(count),
+            unknown => return Err(InstructionError::UnknownDirection(unknown.into())),
+        })
+    }
+}
+
+/// Parse a whole list of instructions, one per line. Blank lines are skipped
+///
+/// # Examples
+/// ```
+/// use aoc2022::day9::*;
+///
+/// assert_eq!(parse_instructions("R 4\nUL 2\n"), Ok(vec![Instruction::Right(4), Instruction::UpLeft(2)]));
+/// assert_eq!(
+///     parse_instructions("R 4\n\nU"),
+///     Err(ParseError { line: 3, kind: InstructionError::MissingCount }),
+/// );
+/// ```
+pub fn parse_instructions(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| line.parse().map_err(|kind| ParseError { line: idx + 1, kind }))
+        .collect()
+}
+
+pub type Position = (i32, i32,);
+
+/// Everything that happened to a rope over the course of a simulation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RopeRun {
+    /// Every position each knot visited, head first
+    pub visited: Vec<HashSet<Position>>,
+
+    /// If asked for, where every knot was after every step, starting with
+    /// where they were before the first step. Each step moves the head one
+    /// square, so an instruction like `R 4` is four steps
+    pub trajectory: Option<Vec<Vec<Position>>>,
+
+    /// The step number at the end of each instruction
+    pub instruction_ends: Vec<usize>,
+}
+
+impl RopeRun {
+    /// Where every knot was after a particular step, if that was recorded
+    pub fn knots_at(&self, step: usize) -> Option<&[Position]> {
+        self.trajectory
+            .as_ref()?
+            .get(step)
+            .map(Vec::as_slice)
+    }
+
+    /// The smallest area that every knot stayed within for the whole run, as
+    /// the bottom left and top right corners
+    pub fn bounds(&self) -> (Position, Position) {
+        self.visited
+            .iter()
+            .flatten()
+            .fold(((0, 0), (0, 0)), |(min, max), &(x, y)| {
+                ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+            })
+    }
+
+    /// Draw the rope after a particular step, the same way the puzzle does,
+    /// over the whole area the rope ever covered
+    pub fn render(&self, step: usize) -> Option<String> {
+        let (min, max) = self.bounds();
+        self.render_within(step, min, max)
+    }
+
+    /// Draw the rope after a particular step, the same way the puzzle does,
+    /// over the area between two corners. The head is `H`, the other knots
+    /// are numbered (except for the tail of a two knot rope, which is `T`)
+    /// and the starting point is `s`. Knots closer to the head are drawn on
+    /// top of the ones behind them
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::day9::*;
+    ///
+    /// let run = simulate_rope_recorded(10, vec![Instruction::Right(4), Instruction::Up(4)], true);
+    ///
+    /// assert_eq!(run.render_within(8, (0, 0), (5, 4)).unwrap(), concat!(
+    ///     "....H.\n",
+    ///     "....1.\n",
+    ///     "..432.\n",
+    ///     ".5....\n",
+    ///     "6.....\n",
+    /// ));
+    ///
+    /// let run = simulate_rope_recorded(2, vec![Instruction::Right(2)], true);
+    /// assert_eq!(run.render(2).unwrap(), "sTH\n");
+    /// ```
+    pub fn render_within(&self, step: usize, min: Position, max: Position) -> Option<String> {
+        let knots = self.knots_at(step)?;
+        let label = |idx: usize| match idx {
+            0 => 'H',
+            1 if knots.len() == 2 => 'T',
+            n => char::from_digit(n as u32, 36).unwrap_or('#'),
+        };
+
+        let mut drawing = String::new();
+        for y in (min.1..=max.1).rev() {
+            for x in min.0..=max.0 {
+                let c = match knots.iter().position(|&knot| knot == (x, y)) {
+                    Some(idx) => label(idx),
+                    None if (x, y) == (0, 0) => 's',
+                    None => '.',
+                };
+                drawing.push(c);
+            }
+            drawing.push('\n');
+        }
+
+        Some(drawing)
+    }
+}
+
+/// Drag every knot after the head along behind the one in front of it, if
+/// they've got further apart than the slack allows
+fn follow_head(knots: &mut [Position], slack: i32) {
+    for idx in 1..knots.len() {
+        let prev = knots[idx-1];
+        let curr = &mut knots[idx];
+
+        let x_distance: i32 = prev.0 - curr.0;
+        let y_distance: i32 = prev.1 - curr.1;
+
+        // Each knot only ever moves one square per step, so one square
+        // towards the knot in front (diagonally if need be) is always enough
+        // to take up the slack again
+        if x_distance.abs() > slack || y_distance.abs() > slack {
+            curr.0 += x_distance.signum();
+            curr.1 += y_distance.signum();
+        }
+    }
+}
+
+/// How a rope is put together, and where it's allowed to go
+///
+/// # Examples
+/// ```
+/// use aoc2022::day9::*;
+///
+/// // With more slack, the tail hardly has to move
+/// let rope = Rope { knots: 2, slack: 3, bounds: None };
+/// let run = rope.simulate(&parse_instructions("R 4\nUR 2").unwrap(), false);
+/// assert_eq!(run.visited[1], [(0, 0), (1, 0), (2, 1), (3, 2)].into());
+///
+/// // The head can't leave the box, but slides along its walls
+/// let rope = Rope { knots: 2, slack: 1, bounds: Some(((0, 0), (2, 2))) };
+/// let run = rope.simulate(&parse_instructions("UR 5\nL 1").unwrap(), true);
+/// assert_eq!(run.knots_at(run.instruction_ends[0]), Some(&[(2, 2), (1, 1)][..]));
+/// assert_eq!(run.knots_at(run.instruction_ends[1]), Some(&[(1, 2), (1, 1)][..]));
+///
+/// // The corners can be given in any order
+/// let rope = Rope { bounds: Some(((2, 2), (0, 0))), ..rope };
+/// let run = rope.simulate(&parse_instructions("UR 5").unwrap(), false);
+/// assert!(run.visited[0].contains(&(2, 2)));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rope {
+    /// How many knots, including the head and tail
+    pub knots: usize,
+
+    /// How far apart (in either direction) each knot can get from the one
+    /// in front before it gets dragged along
+    pub slack: usize,
+
+    /// Opposite corners (either way round) of an area the head can't leave.
+    /// The rope starts at the origin, so the area should include it
+    pub bounds: Option<(Position, Position)>,
+}
+
+impl Rope {
+    /// A rope like the puzzle's, with adjacent knots and nothing in the way
+    pub fn new(knots: usize) -> Self {
+        Self { knots, slack: 1, bounds: None }
+    }
+
+    /// Run the simulation, keeping track of every knot and optionally
+    /// recording the whole trajectory of the rope
+    pub fn simulate(&self, instructions: &[Instruction], record: bool) -> RopeRun {
+        // All of the knots start piled up on each other.
+        // The coordinate space doesn't really matter, so
+        // let's say they start at (0, 0)
+        let mut knots = vec![(0, 0); self.knots];
+        let mut visited = vec![HashSet::from([(0, 0)]); self.knots];
+        let mut trajectory = record.then(|| vec![knots.clone()]);
+        let mut instruction_ends = vec![];
+        let mut steps = 0;
+        let slack = self.slack as i32;
+
+        // For every iteration
+        // - Move the head knot n times in the right direction. It's the
+        //   only knot known to move every step
+        // - Each step, every knot that moves tugs on the knot behind
+        //   it if they are too far apart (if there is one)
+        for instruction in instructions {
+            let (dx, dy) = instruction.delta();
+
+            for _ in 0..instruction.count() {
+                if let Some(head) = knots.first_mut() {
+                    head.0 += dx;
+                    head.1 += dy;
+
+                    if let Some((a, b)) = self.bounds {
+                        head.0 = head.0.clamp(a.0.min(b.0), a.0.max(b.0));
+                        head.1 = head.1.clamp(a.1.min(b.1), a.1.max(b.1));
+                    }
+                }
+
+                follow_head(&mut knots, slack);
+
+                // At the end of every iteration, may as well record where everything is
+                for (knot, seen) in knots.iter().zip(visited.iter_mut()) {
+                    seen.insert(*knot);
+                }
+
+                if let Some(trajectory) = trajectory.as_mut() {
+                    trajectory.push(knots.clone());
+                }
+
+                steps += 1;
+            }
+
+            instruction_ends.push(steps);
+        }
+
+        RopeRun { visited, trajectory, instruction_ends }
+    }
+}
+
+/// Run the simulation, keeping track of every knot rather than just the
+/// tail, and optionally recording the whole trajectory of the rope
+///
+/// # Examples
+/// ```
+/// use aoc2022::day9::*;
+/// let sample = vec![
+///     Instruction::Right(4),
+///     Instruction::Up(4),
+///     Instruction::Left(3),
+///     Instruction::Down(1),
+///     Instruction::Right(4),
+///     Instruction::Down(1),
+///     Instruction::Left(5),
+///     Instruction::Right(2),
+/// ];
+///
+/// let run = simulate_rope_recorded(2, sample, true);
+/// assert_eq!(run.visited[0].len(), 21);
+/// assert_eq!(run.visited[1].len(), 13);
+/// assert_eq!(run.instruction_ends, vec![4, 8, 11, 12, 16, 17, 22, 24]);
+/// assert_eq!(run.knots_at(4), Some(&[(4, 0), (3, 0)][..]));
+/// assert_eq!(run.knots_at(25), None);
+/// ```
+pub fn simulate_rope_recorded(num_knots: usize, instructions: Vec<Instruction>, record: bool) -> RopeRun {
+    Rope::new(num_knots).simulate(&instructions, record)
+}
 
 /// # Examples
 /// ```
 /// use aoc2022::day9::*;
 /// let sample = vec![
-///     ('R', 4),
-///     ('U', 4),
-///     ('L', 3),
-///     ('D', 1),
-///     ('R', 4),
-///     ('D', 1),
-///     ('L', 5),
-///     ('R', 2),
+///     Instruction::Right(4),
+///     Instruction::Up(4),
+///     Instruction::Left(3),
+///     Instruction::Down(1),
+///     Instruction::Right(4),
+///     Instruction::Down(1),
+///     Instruction::Left(5),
+///     Instruction::Right(2),
 /// ];
 ///
 /// assert_eq!(simulate_rope(2, sample.clone()).len(), 13);
 /// assert_eq!(simulate_rope(10, sample.clone()).len(), 1);
 ///
 /// let sample2 = vec![
-///     ('R', 5),
-///     ('U', 8),
-///     ('L', 8),
-///     ('D', 3),
-///     ('R', 17),
-///     ('D', 10),
-///     ('L', 25),
-///     ('U', 20),
+///     Instruction::Right(5),
+///     Instruction::Up(8),
+///     Instruction::Left(8),
+///     Instruction::Down(3),
+///     Instruction::Right(17),
+///     Instruction::Down(10),
+///     Instruction::Left(25),
+///     Instruction::Up(20),
 /// ];
 ///
 /// assert_eq!(simulate_rope(10, sample2).len(), 36);
 ///
 /// ```
 pub fn simulate_rope(num_knots: usize, instructions: Vec<Instruction>) -> HashSet<(i32, i32,)> {
-    // All of the knots start piled up on each other.
-    // The coordinate space doesn't really matter, so
-    // let's say they start at (0, 0)
-    let mut knots = vec![(0, 0); num_knots];
-
-    // Tracking the tail knot, we know it visits at least the origin
-    let mut visited = HashSet::new();
-    visited.insert((0, 0));
-
-    // For every iteration
-    // - Move the head knot n times in the right direction. It's the
-    //   only knot known to move every step
-    // - Each step, every knot that moves tugs on the knot behind
-    //   it if they are no longer adjacent (if there is one)
-    for instruction in instructions {
-        let (direction, count) = instruction;
-
-        for _ in 0..count {
-            // Move the head of the rope
-            match direction {
-                'R' => knots[0].0 += 1,
-                'L' => knots[0].0 -= 1,
-                'U' => knots[0].1 += 1,
-                'D' => knots[0].1 -= 1,
-                x => panic!("Bad direction {x}"),
-            };
-
-            // For each of the non-head knots, see if any movement
-            // of the preceding knot should drag this one along
-            for idx in 1..knots.len() {
-                let prev = knots[idx-1];
-                let curr = &mut knots[idx];
-
-                let x_distance: i32 = prev.0 - curr.0;
-                let y_distance: i32 = prev.1 - curr.1;
-
-                if x_distance.abs() > 1 || y_distance.abs() > 1 {
-                    if curr.0 != prev.0 && curr.1 != prev.1 {
-                        // Pulling diagonally, so update both coords
-                        curr.0 += x_distance.signum();
-                        curr.1 += y_distance.signum();
-                    } else if x_distance.abs() > 1 {
-                        // Pulling horizontally
-                        curr.0 += x_distance.signum();
-                    } else {
-                        // Pulling vertically
-                        curr.1 += y_distance.signum();
-                    }
-                }
-            }
-
-            // At the end of every iteration, may as well record where the tail is
-            visited.insert(knots[num_knots - 1]);
-        }
-    }
-    
-    visited
+    simulate_rope_recorded(num_knots, instructions, false)
+        .visited
+        .pop()
+        .unwrap_or_default()
 }
 
 
 #[cfg(test)]
 mod answers {
     use super::*;
+    use std::sync::LazyLock;
 
-    pub fn parse_instruction(input: &str) -> Instruction {
-        let direction = input.chars().next().unwrap();
-        let count = input.split_ascii_whitespace().nth(1).unwrap().parse().unwrap();
-
-        (direction, count)
-    }
+    static INPUT: LazyLock<Vec<Instruction>> = LazyLock::new(|| {
+        parse_instructions(include_str!("./input/day9.txt")).unwrap()
+    });
 
     #[test]
     fn problem1() {
-        let input = include_str!("./input/day9.txt").lines().map(parse_instruction).collect();
-        let results = simulate_rope(2, input);
+        let results = simulate_rope(2, INPUT.clone());
 
         assert_eq!(results.len(), 6087);
     }
 
     #[test]
     fn problem2() {
-        let input = include_str!("./input/day9.txt").lines().map(parse_instruction).collect();
-        let results = simulate_rope(10, input);
+        let results = simulate_rope(10, INPUT.clone());
 
         assert_eq!(results.len(), 2493);
     }
@@ -0,0 +1,309 @@
+//! Interval sets - collections of disjoint, inclusive integer ranges
+
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+
+/// The bits of integer arithmetic an [IntervalSet] needs
+pub trait Integer: Copy + Ord + Debug {
+    /// The next value up, if there is one
+    fn succ(self) -> Option<Self>;
+
+    /// The next value down, if there is one
+    fn pred(self) -> Option<Self>;
+
+    /// How many values lie in `lo..=hi`, assuming `lo <= hi`
+    fn span(lo: Self, hi: Self) -> u128;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn succ(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn pred(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+
+                fn span(lo: Self, hi: Self) -> u128 {
+                    (hi as i128 - lo as i128) as u128 + 1
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// A set of integers, stored as a sorted list of disjoint ranges. Ranges
+/// that overlap or touch are always merged, so `1..=2` and `3..=4` are
+/// stored as `1..=4`
+///
+/// # Examples
+/// ```
+/// use aoc2022::interval::*;
+///
+/// let mut set = IntervalSet::new();
+/// set.insert(1..=3);
+/// set.insert(10..=12);
+/// set.insert(4..=5);
+///
+/// assert_eq!(set.iter().collect::<Vec<_>>(), vec![1..=5, 10..=12]);
+/// assert_eq!(set.len(), 8);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    ranges: Vec<(T, T)>,
+}
+
+impl<T: Integer> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self { ranges: vec![] }
+    }
+
+    /// Add a range to the set, merging it with anything it overlaps or
+    /// touches. Empty ranges are ignored
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        let (mut start, mut end) = range.into_inner();
+        if start > end {
+            return;
+        }
+
+        // Everything strictly before `first` ends too early to touch the new
+        // range, and everything from `last` on starts too late
+        let first = self.ranges.partition_point(|&(_, e)| e.succ().is_some_and(|e| e < start));
+        let last = self.ranges.partition_point(|&(s, _)| s.pred().map_or(true, |s| s <= end));
+
+        if first < last {
+            start = start.min(self.ranges[first].0);
+            end = end.max(self.ranges[last - 1].1);
+        }
+
+        self.ranges.splice(first..last, [(start, end)]);
+    }
+
+    /// Add everything in another set to this one
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let mut set = IntervalSet::from_iter([1..=3, 8..=9]);
+    /// set.merge(&IntervalSet::from_iter([2..=5, 7..=7]));
+    ///
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![1..=5, 7..=9]);
+    /// ```
+    pub fn merge(&mut self, other: &Self) {
+        for range in other.iter() {
+            self.insert(range);
+        }
+    }
+
+    /// Everything that is in both this set and the other
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let lhs = IntervalSet::from_iter([1..=5, 10..=20]);
+    /// let rhs = IntervalSet::from_iter([4..=12, 18..=30]);
+    ///
+    /// let both = lhs.intersection(&rhs);
+    /// assert_eq!(both.iter().collect::<Vec<_>>(), vec![4..=5, 10..=12, 18..=20]);
+    /// ```
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+
+        // Classic sorted merge - whichever range finishes first can't
+        // overlap anything further along in the other set
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a_start, a_end) = self.ranges[i];
+            let (b_start, b_end) = other.ranges[j];
+
+            let start = a_start.max(b_start);
+            let end = a_end.min(b_end);
+            if start <= end {
+                ranges.push((start, end));
+            }
+
+            if a_end < b_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { ranges }
+    }
+
+    /// Everything that is in this set but not the other
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let lhs = IntervalSet::from_iter([1..=10]);
+    /// let rhs = IntervalSet::from_iter([3..=4, 8..=12]);
+    ///
+    /// let rest = lhs.difference(&rhs);
+    /// assert_eq!(rest.iter().collect::<Vec<_>>(), vec![1..=2, 5..=7]);
+    /// ```
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let mut j = 0;
+
+        for &(start, end) in &self.ranges {
+            // Skip anything in the other set that finishes before this range
+            while j < other.ranges.len() && other.ranges[j].1 < start {
+                j += 1;
+            }
+
+            // Carve out each overlapping range in turn. The last one might
+            // carry on into the next range of ours, so don't consume it
+            let mut start = Some(start);
+            let mut k = j;
+            while let Some(s) = start {
+                match other.ranges.get(k) {
+                    Some(&(o_start, o_end)) if o_start <= end => {
+                        if s < o_start {
+                            ranges.push((s, o_start.pred().unwrap()));
+                        }
+                        start = o_end.succ().filter(|&next| next <= end);
+                        k += 1;
+                    },
+                    _ => {
+                        ranges.push((s, end));
+                        start = None;
+                    },
+                }
+            }
+        }
+
+        Self { ranges }
+    }
+
+    /// Whether a single value is in the set
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let set = IntervalSet::from_iter([1..=3, 7..=9]);
+    /// assert!(set.contains(2));
+    /// assert!(!set.contains(5));
+    /// ```
+    pub fn contains(&self, value: T) -> bool {
+        self.contains_range(&(value..=value))
+    }
+
+    /// Whether every value in a range is in the set. Empty ranges are
+    /// trivially contained
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let set = IntervalSet::from_iter([1..=3, 4..=6]);
+    /// assert!(set.contains_range(&(2..=5)));
+    /// assert!(!set.contains_range(&(5..=7)));
+    /// ```
+    pub fn contains_range(&self, range: &RangeInclusive<T>) -> bool {
+        let (start, end) = (*range.start(), *range.end());
+        if start > end {
+            return true;
+        }
+
+        let idx = self.ranges.partition_point(|&(_, e)| e < start);
+        self.ranges
+            .get(idx)
+            .is_some_and(|&(s, e)| s <= start && end <= e)
+    }
+
+    /// Whether every value in the other set is in this one
+    ///
+    /// # Examples
+    /// ```
+    /// use aoc2022::interval::*;
+    ///
+    /// let set = IntervalSet::from_iter([1..=10]);
+    /// assert!(set.is_superset(&IntervalSet::from_iter([2..=3, 5..=9])));
+    /// assert!(!set.is_superset(&IntervalSet::from_iter([0..=1])));
+    /// ```
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other
+            .iter()
+            .all(|range| self.contains_range(&range))
+    }
+
+    /// How many values are in the set, in total
+    pub fn len(&self) -> u128 {
+        self.ranges
+            .iter()
+            .map(|&(s, e)| T::span(s, e))
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The disjoint ranges making up the set, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = RangeInclusive<T>> + '_ {
+        self.ranges
+            .iter()
+            .map(|&(s, e)| s..=e)
+    }
+}
+
+impl<T: Integer> From<RangeInclusive<T>> for IntervalSet<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        let mut set = Self::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T: Integer> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Integer> Extend<RangeInclusive<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = RangeInclusive<T>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_at_the_limits() {
+        let mut set = IntervalSet::from_iter([0u8..=10, 250..=255]);
+        set.insert(11..=249);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0..=255]);
+        assert_eq!(set.len(), 256);
+
+        let rest = set.difference(&IntervalSet::from_iter([0..=0, 255..=255]));
+        assert_eq!(rest.iter().collect::<Vec<_>>(), vec![1..=254]);
+        assert!(set.difference(&set).is_empty());
+    }
+
+    #[test]
+    fn difference_spanning_several_ranges() {
+        let lhs = IntervalSet::from_iter([1..=5, 8..=12, 15..=20]);
+        let rhs = IntervalSet::from_iter([4..=9, 11..=16]);
+
+        let rest = lhs.difference(&rhs);
+        assert_eq!(rest.iter().collect::<Vec<_>>(), vec![1..=3, 10..=10, 17..=20]);
+    }
+}
@@ -36,3 +36,6 @@ pub mod day21;
 pub mod day23;
 pub mod day24;
 pub mod day25;
+
+pub mod interval;
+pub mod line_error;
@@ -0,0 +1,26 @@
+//! Errors in puzzle input that can be pinned down to a single line
+
+use std::fmt::{Debug, Display};
+
+/// Something wrong with the input, and which (1-based) line it's on
+///
+/// # Examples
+/// ```
+/// use aoc2022::line_error::LineError;
+///
+/// let error = LineError { line: 3, kind: "missing comma" };
+/// assert_eq!(error.to_string(), "line 3: missing comma");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineError<K> {
+    pub line: usize,
+    pub kind: K,
+}
+
+impl<K: Display> Display for LineError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl<K: Debug + Display> std::error::Error for LineError<K> {}